@@ -1,8 +1,12 @@
 use core::fmt::Debug;
 use std::cell::RefCell;
+use std::collections::{HashMap, VecDeque};
 use std::io::{Write, Seek, SeekFrom};
 use std::fs::File;
+use std::os::unix::process::ExitStatusExt;
+use std::process::{Child, ExitStatus};
 use std::rc::Rc;
+use std::thread;
 use std::time;
 
 use anyhow::Result;
@@ -17,19 +21,156 @@ use ratatui::{
     DefaultTerminal, Frame,
 };
 
-use crate::app_data::AppData;
+use crate::app_data::{
+    ENGINE_CLASSES, engine_class, AppData, AppDataEngineStats};
+use crate::app_summary::AppSummary;
+use crate::app_alerts::AppAlerts;
+use crate::app_config::{AppConfig, AppTheme};
 use crate::Args;
 
 mod main_screen;
 mod drm_client_screen;
+mod dev_details_screen;
 use main_screen::MainScreen;
 
 
+#[derive(Debug)]
+pub struct AppEngineView
+{
+    pub name: String,
+    pub capacity: u32,
+    pub usage: VecDeque<f64>,
+}
+
+impl AppEngineView
+{
+    pub fn label(&self) -> String
+    {
+        if self.capacity > 1 {
+            format!("{} x{}", self.name.to_uppercase(), self.capacity)
+        } else {
+            self.name.to_uppercase()
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct AppModel
 {
     pub data: AppData,
     pub args: Args,
+    pub engs_grouped: bool,
+    pub engs_busiest: bool,
+    pub engs_freq_weighted: bool,
+    pub config: AppConfig,
+    pub theme: AppTheme,
+}
+
+impl AppModel
+{
+    // fdinfo only has the busy time summed over all instances of an engine,
+    // so the busiest instance is estimated as if all work was on one of them
+    fn engine_usage(&self, est: &AppDataEngineStats, idx: usize) -> f64
+    {
+        let mut res = est.usage[idx];
+
+        // weight by the engine frequency at the time, if it reports one
+        if self.engs_freq_weighted {
+            if let Some((cur, mx)) = est.freqs(idx) {
+                if mx > 0 {
+                    res = res * cur as f64 / mx as f64;
+                }
+            }
+        }
+
+        if self.engs_busiest {
+            res *= est.capacity as f64;
+            if res > 100.0 {
+                res = 100.0;
+            }
+        }
+
+        res
+    }
+
+    // usage of the given engines as displayed: one entry per engine or per
+    // engine class (if grouped), either normalized to the engines capacity
+    // or for the busiest engine instance
+    pub fn engines_view(&self, eng_names: &Vec<&String>,
+        eng_stats: &HashMap<String, AppDataEngineStats>) -> Vec<AppEngineView>
+    {
+        let mut res: Vec<AppEngineView> = Vec::new();
+
+        if !self.engs_grouped {
+            for en in eng_names.iter() {
+                let est = eng_stats.get(*en).unwrap();
+                res.push(AppEngineView {
+                    name: en.to_string(),
+                    capacity: est.capacity,
+                    usage: (0..est.usage.len())
+                        .map(|i| self.engine_usage(est, i)).collect(),
+                });
+            }
+
+            return res;
+        }
+
+        let mut classes: Vec<&str> = ENGINE_CLASSES.iter()
+            .map(|(cls, _)| *cls).collect();
+        for en in eng_names.iter() {
+            let cls = engine_class(en);
+            if !classes.contains(&cls) {
+                classes.push(cls);
+            }
+        }
+
+        for cls in classes.iter() {
+            let ests: Vec<&AppDataEngineStats> = eng_names.iter()
+                .filter(|en| engine_class(en) == *cls)
+                .map(|en| eng_stats.get(*en).unwrap())
+                .collect();
+            if ests.is_empty() {
+                continue;
+            }
+
+            // engines may have joined later, align their stats on the end
+            let nr_vals = ests.iter().map(|e| e.usage.len()).max().unwrap();
+            let capacity: u32 = ests.iter().map(|e| e.capacity).sum();
+            let mut usage = VecDeque::new();
+            for i in 0..nr_vals {
+                let mut val: f64 = 0.0;
+                for est in ests.iter() {
+                    let off = nr_vals - est.usage.len();
+                    let eu = if i >= off {
+                        self.engine_usage(est, i - off) } else { 0.0 };
+                    if self.engs_busiest {
+                        val = val.max(eu);
+                    } else {
+                        val += eu * est.capacity as f64 / capacity as f64;
+                    }
+                }
+                usage.push_back(val);
+            }
+
+            res.push(AppEngineView {
+                name: cls.to_string(),
+                capacity,
+                usage,
+            });
+        }
+
+        res
+    }
+
+    pub fn engines_usage_title(&self) -> &str
+    {
+        match (self.engs_busiest, self.engs_freq_weighted) {
+            (false, false) => "Usage (%)",
+            (true, false) => "Busiest (%)",
+            (false, true) => "Usage@freq (%)",
+            (true, true) => "Busiest@freq (%)",
+        }
+    }
 }
 
 #[derive(Debug)]
@@ -94,11 +235,52 @@ impl AppScreens
     }
 }
 
+// command run by qmassa, killed (and reaped) if it's dropped before the
+// command exits, e.g. on errors refreshing the stats
+#[derive(Debug)]
+pub struct AppChild
+{
+    child: Child,
+    exited: bool,
+}
+
+impl Drop for AppChild
+{
+    fn drop(&mut self)
+    {
+        if !self.exited {
+            let _ = self.child.kill();
+            let _ = self.child.wait();
+        }
+    }
+}
+
+impl AppChild
+{
+    fn try_wait(&mut self) -> Result<Option<ExitStatus>>
+    {
+        let res = self.child.try_wait()?;
+        self.exited = res.is_some();
+
+        Ok(res)
+    }
+
+    pub fn from(child: Child) -> AppChild
+    {
+        AppChild {
+            child,
+            exited: false,
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct App
 {
     model: Rc<RefCell<AppModel>>,
     screens: AppScreens,
+    summary: AppSummary,
+    alerts: AppAlerts,
     exit: bool,
 }
 
@@ -126,15 +308,49 @@ impl App
         vstr
     }
 
-    fn gauge_colored_from(label: Span, ratio: f64) -> Gauge
+    fn energy_string(val: f64) -> String
+    {
+        format!("{:.1} J, {:.4} Wh", val, val / 3600.0)
+    }
+
+    fn gauge_colored_from<'a>(theme: &AppTheme,
+        label: Span<'a>, ratio: f64) -> Gauge<'a>
+    {
+        let rt = if ratio > 1.0 { 1.0 } else { ratio };
+        let gstyle = if rt > theme.gauge_red_ratio {
+            theme.gauge_red
+        } else if rt > theme.gauge_orange_ratio {
+            theme.gauge_orange
+        } else {
+            theme.gauge_green
+        };
+
+        Gauge::default()
+            .label(label)
+            .gauge_style(gstyle)
+            .use_unicode(true)
+            .ratio(rt)
+    }
+
+    fn gauge_not_available() -> Gauge<'static>
+    {
+        Gauge::default()
+            .label(Span::styled("N/A", Style::new().white()))
+            .gauge_style(tailwind::GRAY.c500)
+            .use_unicode(true)
+            .ratio(0.0)
+    }
+
+    fn gauge_temp_from<'a>(theme: &AppTheme,
+        label: Span<'a>, ratio: f64) -> Gauge<'a>
     {
         let rt = if ratio > 1.0 { 1.0 } else { ratio };
-        let gstyle = if rt > 0.7 {
-            tailwind::RED.c500
-        } else if rt > 0.3 {
-            tailwind::ORANGE.c500
+        let gstyle = if rt > 0.9 {
+            theme.gauge_red
+        } else if rt > 0.75 {
+            theme.gauge_orange
         } else {
-            tailwind::GREEN.c500
+            theme.gauge_green
         };
 
         Gauge::default()
@@ -153,14 +369,19 @@ impl App
             Constraint::Length(1),
         ]).areas(frame.area());
 
+        let model = self.model.borrow();
+        let (title_color, border_color) =
+            (model.theme.title, model.theme.border);
+        drop(model);
+
         let prog_name = Line::from(vec![
             " qmassa! v".into(),
             env!("CARGO_PKG_VERSION").into(),
             " ".into(),])
-            .style(Style::new().light_blue().bold().on_black());
+            .style(Style::new().fg(title_color).bold().on_black());
         let menu_blk = Block::bordered()
             .border_type(BorderType::Thick)
-            .border_style(Style::new().cyan().bold().on_black())
+            .border_style(Style::new().fg(border_color).bold().on_black())
             .title_top(prog_name.alignment(Alignment::Center));
         let tab_area = menu_blk.inner(menu_area);
 
@@ -168,6 +389,16 @@ impl App
         let scr = self.screens.current().unwrap();  // always >= 1 screens
 
         let mut st_bar_text = scr.status_bar_text();
+        let firing = self.alerts.firing();
+        if let Some(alert) = firing.first() {
+            let mut atext = format!(" ALERT: {} on {} ",
+                alert.rule, alert.target());
+            if firing.len() > 1 {
+                atext.push_str(&format!("(+{} more) ", firing.len() - 1));
+            }
+            st_bar_text.insert(0, Span::styled(atext,
+                Style::new().white().on_red().bold()));
+        }
         if st_len > 1 {
             st_bar_text.push(" (Esc) Back".white().bold());
         }
@@ -183,7 +414,7 @@ impl App
         frame.render_widget(
             Block::new().borders(Borders::TOP)
                 .border_type(BorderType::Thick)
-                .border_style(Style::new().cyan().bold().on_black())
+                .border_style(Style::new().fg(border_color).bold().on_black())
                 .title_top(instr.alignment(Alignment::Center)),
             status_bar);
 
@@ -231,21 +462,82 @@ impl App
         Ok(())
     }
 
-    fn do_run(&mut self, terminal: &mut DefaultTerminal) -> Result<()>
+    fn json_start(&self) -> Result<Option<File>>
     {
         let model = self.model.borrow();
-        let ival = time::Duration::from_millis(model.args.ms_interval);
-        let max_iterations = model.args.nr_iterations;
 
-        let mut json_file: Option<File> = None;
         if let Some(fname) = &model.args.to_json {
             let mut f = File::create(fname)?;
             // start json data array
             writeln!(f, "[\n]")?;
-            json_file = Some(f);
+            return Ok(Some(f));
+        }
+
+        Ok(None)
+    }
+
+    fn json_append(&self, jf: &mut File, nr: i32) -> Result<()>
+    {
+        let model = self.model.borrow();
+
+        // overwrite last 2 bytes == "]\n" with new state
+        jf.seek(SeekFrom::End(-2))?;
+        // nr is 1 on the first state, which has no previous one to separate
+        if nr > 1 {
+            writeln!(jf, ",")?;
+        }
+        serde_json::to_writer_pretty(&mut *jf, model.data.state())?;
+        writeln!(jf, "\n]")?;
+
+        Ok(())
+    }
+
+    // last element of the JSON data array, after all states
+    fn json_append_summary(&self, jf: &mut File) -> Result<()>
+    {
+        jf.seek(SeekFrom::End(-2))?;
+        if self.summary.nr_samples > 0 {
+            writeln!(jf, ",")?;
         }
+        serde_json::to_writer_pretty(&mut *jf,
+            &serde_json::json!({ "summary": &self.summary }))?;
+        writeln!(jf, "\n]")?;
+
+        Ok(())
+    }
+
+    // throttle reasons residency of each device over the whole session
+    fn csv_write_throttle(&self) -> Result<()>
+    {
+        let model = self.model.borrow();
+
+        if let Some(fname) = &model.args.throttle_csv {
+            let mut f = File::create(fname)?;
+            writeln!(f, "pci_dev,reason,active_ms,total_ms,residency")?;
+            for ds in model.data.devices().iter() {
+                if let Some(thr) = &ds.dev_stats.throttle {
+                    for rsn in thr.reasons.iter() {
+                        writeln!(f, "{},{},{},{},{:.2}", ds.pci_dev, rsn.name,
+                            rsn.active_ms, thr.total_ms, rsn.residency)?;
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn do_run(&mut self, terminal: &mut DefaultTerminal) -> Result<()>
+    {
+        let model = self.model.borrow();
+        let ival = time::Duration::from_millis(model.args.ms_interval);
+        let max_iterations = model.args.nr_iterations;
         drop(model);
 
+        let mut json_file = self.json_start()?;
+        // summary only printed (or saved) for limited or saved runs
+        let with_summary = max_iterations >= 0 || json_file.is_some();
+
         let mut last_check = time::Instant::now();
         let mut timer = time::Duration::ZERO;
         let mut nr = 0;
@@ -264,20 +556,18 @@ impl App
 
                 // refresh stats and update accounting
                 model.data.refresh()?;
+                if with_summary {
+                    self.summary.update(&model.data);
+                }
+                self.alerts.update(&model.data);
                 timer = ival;
                 nr += 1;
+                drop(model);
 
                 // write new state to JSON file (if needed)
                 if let Some(jf) = &mut json_file {
-                    // overwrite last 2 bytes == "]\n" with new state
-                    jf.seek(SeekFrom::End(-2))?;
-                    if nr >= 1 {
-                        writeln!(jf, ",")?;
-                    }
-                    serde_json::to_writer_pretty(&mut *jf, model.data.state())?;
-                    writeln!(jf, "\n]")?;
+                    self.json_append(jf, nr)?;
                 }
-                drop(model);
             } else {
                 timer -= elapsed;
             }
@@ -286,6 +576,11 @@ impl App
             self.handle_events(timer)?;
         }
 
+        if let Some(jf) = &mut json_file {
+            self.json_append_summary(jf)?;
+        }
+        self.csv_write_throttle()?;
+
         Ok(())
     }
 
@@ -298,18 +593,95 @@ impl App
         let res = self.do_run(&mut terminal);
         ratatui::restore();
 
+        // summary of the whole run if it was limited or saved
+        let model = self.model.borrow();
+        if res.is_ok() &&
+            (model.args.nr_iterations >= 0 || model.args.to_json.is_some()) {
+            eprint!("{}", self.summary);
+        }
+
         res
     }
 
-    pub fn from(data: AppData, args: Args) -> App
+    // no UI, refreshes stats until the command being run exits and prints
+    // a summary of its usage, returns the command exit code
+    pub fn run_command(&mut self, mut child: AppChild) -> Result<i32>
+    {
+        let model = self.model.borrow();
+        let ival = time::Duration::from_millis(model.args.ms_interval);
+        drop(model);
+
+        let mut json_file = self.json_start()?;
+        let mut nr = 0;
+
+        let status = loop {
+            let mut model = self.model.borrow_mut();
+            model.data.refresh()?;
+            self.summary.update(&model.data);
+            self.alerts.update(&model.data);
+            nr += 1;
+            drop(model);
+
+            if let Some(jf) = &mut json_file {
+                self.json_append(jf, nr)?;
+            }
+
+            // wait for next update, unless the command exits earlier
+            let start = time::Instant::now();
+            let mut res = child.try_wait()?;
+            while res.is_none() && start.elapsed() < ival {
+                thread::sleep(time::Duration::from_millis(10));
+                res = child.try_wait()?;
+            }
+            if let Some(st) = res {
+                break st;
+            }
+        };
+
+        let code = if let Some(c) = status.code() {
+            c } else { 128 + status.signal().unwrap_or(0) };
+
+        eprintln!("\nqmassa: command exited with code {} after {:.1} s",
+            code, *self.model.borrow().data.timestamps().back()
+                .unwrap_or(&0) as f64 / 1000.0);
+        eprint!("{}", self.summary);
+
+        if let Some(jf) = &mut json_file {
+            self.json_append_summary(jf)?;
+        }
+        self.csv_write_throttle()?;
+
+        Ok(code)
+    }
+
+    pub fn summary(&self) -> &AppSummary
+    {
+        &self.summary
+    }
+
+    pub fn set_alerts(&mut self, alerts: AppAlerts)
     {
-        App {
+        self.alerts = alerts;
+    }
+
+    pub fn from(data: AppData, args: Args, config: AppConfig) -> Result<App>
+    {
+        let theme = AppTheme::from(&config)?;
+
+        Ok(App {
             model: Rc::new(RefCell::new(AppModel {
                 data,
                 args,
+                engs_grouped: false,
+                engs_busiest: false,
+                engs_freq_weighted: false,
+                config,
+                theme,
             })),
             screens: AppScreens::new(),
+            summary: AppSummary::new(),
+            alerts: AppAlerts::new(),
             exit: false,
-        }
+        })
     }
 }
@@ -0,0 +1,274 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use crossterm::event::KeyEvent;
+use ratatui::{
+    layout::{Alignment, Constraint, Direction, Layout, Rect},
+    style::{palette::tailwind, Style, Stylize},
+    text::{Span, Line},
+    widgets::{Bar, BarChart, BarGroup, Block, Borders, BorderType, Row, Table},
+    Frame,
+};
+
+use crate::app_data::AppDataDeviceState;
+use crate::app::{App, AppModel, Screen, ScreenAction};
+
+
+#[derive(Debug)]
+pub struct DevDetailsScreen
+{
+    model: Rc<RefCell<AppModel>>,
+    pci_dev: String,
+}
+
+impl Screen for DevDetailsScreen
+{
+    fn name(&self) -> &str
+    {
+        "Device Details Screen"
+    }
+
+    fn draw(&mut self, frame: &mut Frame, tab_area: Rect, main_area: Rect)
+    {
+        let model = self.model.borrow();
+        let dinfo = model.data.get_device(&self.pci_dev);
+        if dinfo.is_none() {
+            frame.render_widget(Line::from(
+                    format!("No DRM GPU device at: {:?}", self.pci_dev))
+                .alignment(Alignment::Center), tab_area);
+            return;
+        }
+        let dinfo = dinfo.unwrap();
+
+        // render tab area with DRM device basic info
+        let widths = vec![Constraint::Fill(1); 3];
+        let rows = [Row::new([
+            Line::from(vec![
+                "DEV: ".white().bold(),
+                dinfo.pci_dev.clone().into()])
+            .alignment(Alignment::Center),
+            Line::from(vec![
+                "DRIVER: ".white().bold(),
+                dinfo.drv_name.clone().into()])
+            .alignment(Alignment::Center),
+            Line::from(vec![
+                "TYPE: ".white().bold(),
+                dinfo.dev_type.clone().into()])
+            .alignment(Alignment::Center),
+        ])];
+        frame.render_widget(Table::new(rows, widths)
+            .style(Style::new().white().on_black())
+            .column_spacing(1),
+            tab_area);
+
+        let info_rows = self.info_rows(dinfo);
+        let [info_area, sep, tis_area] = Layout::vertical(vec![
+            Constraint::Length(info_rows.len() as u16),
+            Constraint::Length(1),
+            Constraint::Fill(1),
+        ]).areas(main_area);
+
+        // render device info table
+        let widths = vec![Constraint::Length(20), Constraint::Fill(1)];
+        frame.render_widget(Table::new(info_rows, widths)
+            .style(Style::new().white().on_black())
+            .column_spacing(1),
+            info_area);
+
+        // render separator line
+        frame.render_widget(Block::new().borders(Borders::TOP)
+            .border_type(BorderType::Plain)
+            .border_style(Style::new().white().on_black()),
+            sep);
+
+        // render devfreq time in state histogram
+        self.render_time_in_state(dinfo, frame, tis_area);
+    }
+
+    fn handle_key_event(
+        &mut self, _key_event: KeyEvent) -> Option<ScreenAction>
+    {
+        None
+    }
+
+    fn status_bar_text(&mut self) -> Vec<Span>
+    {
+        Vec::new()
+    }
+}
+
+impl DevDetailsScreen
+{
+    fn info_row<'a>(label: &'a str, val: String) -> Row<'a>
+    {
+        Row::new([
+            Line::from(label).style(Style::new().magenta().bold()),
+            Line::from(val).style(Style::new().white()),
+        ])
+    }
+
+    fn vf_param(val: Option<u64>, is_mem: bool) -> String
+    {
+        match val {
+            Some(v) if is_mem => App::short_mem_string(v),
+            Some(v) => v.to_string(),
+            None => String::from("N/A"),
+        }
+    }
+
+    fn info_rows(&self, dinfo: &AppDataDeviceState) -> Vec<Row<'_>>
+    {
+        let mut rows = Vec::new();
+
+        rows.push(DevDetailsScreen::info_row("Device:",
+            dinfo.vdr_dev_rev.clone()));
+        if !dinfo.subsys.is_empty() {
+            rows.push(DevDetailsScreen::info_row("Subsystem:",
+                dinfo.subsys.clone()));
+        }
+        rows.push(DevDetailsScreen::info_row("DRM nodes:",
+            dinfo.dev_nodes.clone()));
+        rows.push(DevDetailsScreen::info_row("Driver version:",
+            dinfo.drv_version.clone()));
+
+        if let Some(pi) = &dinfo.pci_info {
+            let na = |v: &String| if v.is_empty() {
+                String::from("N/A") } else { v.clone() };
+            rows.push(DevDetailsScreen::info_row("PCIe link:",
+                format!("{} x{} (max: {} x{})",
+                    na(&pi.cur_link_speed), na(&pi.cur_link_width),
+                    na(&pi.max_link_speed), na(&pi.max_link_width))));
+            rows.push(DevDetailsScreen::info_row("NUMA node:",
+                if pi.numa_node < 0 {
+                    String::from("N/A") } else { pi.numa_node.to_string() }));
+            rows.push(DevDetailsScreen::info_row("IOMMU group:",
+                na(&pi.iommu_group)));
+        }
+
+        for (idx, fw) in dinfo.firmwares.iter().enumerate() {
+            rows.push(DevDetailsScreen::info_row(
+                if idx == 0 { "Firmwares:" } else { "" },
+                format!("{}: {}", fw.name, fw.version)));
+        }
+
+        if let Some(df) = &dinfo.devfreq {
+            rows.push(DevDetailsScreen::info_row("Devfreq governor:",
+                if df.governor.is_empty() {
+                    String::from("N/A") } else { df.governor.clone() }));
+            rows.push(DevDetailsScreen::info_row("Devfreq freqs:",
+                format!("cur {} MHz, min {} MHz, max {} MHz",
+                    df.cur_freq, df.min_freq, df.max_freq)));
+            rows.push(DevDetailsScreen::info_row("Available freqs:",
+                if df.available_freqs.is_empty() { String::from("N/A") }
+                else {
+                    df.available_freqs.iter()
+                        .map(|f| f.to_string())
+                        .collect::<Vec<String>>()
+                        .join(" ") + " MHz"
+                }));
+            rows.push(DevDetailsScreen::info_row("Transitions:",
+                df.total_trans.to_string()));
+        }
+
+        if let Some(bs) = &dinfo.block_stats {
+            let acts: Vec<String> = [
+                ("GFX", bs.gfx_activity),
+                ("MEM", bs.mem_activity),
+                ("MM", bs.mm_activity),
+            ].iter()
+                .filter_map(|(n, v)| v.map(|a| format!("{} {:.1}%", n, a)))
+                .collect();
+            let clks: Vec<String> = [
+                ("SOC", bs.soc_freq),
+                ("VCLK", bs.vclk_freq),
+                ("DCLK", bs.dclk_freq),
+            ].iter()
+                .filter_map(|(n, v)| v.map(|f| format!("{} {} MHz", n, f)))
+                .collect();
+
+            rows.push(DevDetailsScreen::info_row("Blocks activity:",
+                if acts.is_empty() {
+                    String::from("N/A") } else { acts.join(", ") }));
+            rows.push(DevDetailsScreen::info_row("Blocks clocks:",
+                if clks.is_empty() {
+                    String::from("N/A") } else { clks.join(", ") }));
+        }
+
+        if let Some(sr) = &dinfo.sriov {
+            if sr.is_vf() {
+                rows.push(DevDetailsScreen::info_row("SR-IOV:",
+                    format!("VF{} of PF {}", sr.vf_id, sr.pf_dev)));
+            } else {
+                rows.push(DevDetailsScreen::info_row("SR-IOV:",
+                    format!("PF with {}/{} VFs enabled{}{}",
+                        sr.vf_devs.len(), sr.total_vfs,
+                        if sr.vf_devs.is_empty() { "" } else { ": " },
+                        sr.vf_devs.join(", "))));
+            }
+            for (idx, vp) in sr.vf_provs.iter().enumerate() {
+                rows.push(DevDetailsScreen::info_row(
+                    if idx == 0 { "VFs provisioning:" } else { "" },
+                    format!("VF{}: GGTT {}, LMEM {}, contexts {}, \
+                        doorbells {}, exec quantum {} ms, \
+                        preempt timeout {} us", vp.vf_id,
+                        DevDetailsScreen::vf_param(vp.ggtt_quota, true),
+                        DevDetailsScreen::vf_param(vp.lmem_quota, true),
+                        DevDetailsScreen::vf_param(vp.contexts_quota, false),
+                        DevDetailsScreen::vf_param(vp.doorbells_quota, false),
+                        DevDetailsScreen::vf_param(vp.exec_quantum_ms, false),
+                        DevDetailsScreen::vf_param(vp.preempt_timeout_us,
+                            false))));
+            }
+        }
+
+        rows
+    }
+
+    fn render_time_in_state(&self,
+        dinfo: &AppDataDeviceState, frame: &mut Frame, area: Rect)
+    {
+        let tis = match &dinfo.devfreq {
+            Some(df) if !df.time_in_state.is_empty() => &df.time_in_state,
+            _ => {
+                frame.render_widget(Line::from("No devfreq time in state \
+                    information").alignment(Alignment::Center), area);
+                return;
+            }
+        };
+
+        let total: u64 = tis.iter().map(|(_, tm)| tm).sum();
+        let mut bars = Vec::new();
+        for (fq, tm) in tis.iter() {
+            let pct = if total > 0 {
+                *tm as f64 * 100.0 / total as f64 } else { 0.0 };
+            bars.push(Bar::default()
+                .value((pct * 10.0) as u64)
+                .label(Line::from(format!("{:>5} MHz", fq)))
+                .text_value(format!("{:.1}%", pct))
+                .style(tailwind::BLUE.c700)
+                .value_style(Style::new().white().on_blue()));
+        }
+
+        let tis_blk = Block::new()
+            .borders(Borders::NONE)
+            .title_top(Line::from("Time in state").centered())
+            .style(Style::new().white().on_black());
+
+        frame.render_widget(BarChart::default()
+            .block(tis_blk)
+            .direction(Direction::Horizontal)
+            .data(BarGroup::default().bars(&bars))
+            .bar_width(1)
+            .bar_gap(0)
+            .max(1000),
+            area);
+    }
+
+    pub fn new(model: Rc<RefCell<AppModel>>, pci_dev: String) -> Box<dyn Screen>
+    {
+        Box::new(DevDetailsScreen {
+            model,
+            pci_dev,
+        })
+    }
+}
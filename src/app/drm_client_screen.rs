@@ -45,8 +45,10 @@ impl DrmClientSelected
 
 const CLIENT_STATS_MEMINFO: u8 = 0;
 const CLIENT_STATS_ENGINES: u8 = 1;
-const CLIENT_STATS_CPU: u8 = 2;
-const CLIENT_STATS_TOTAL: u8 = 3;
+const CLIENT_STATS_FREQS: u8 = 2;
+const CLIENT_STATS_POWER: u8 = 3;
+const CLIENT_STATS_CPU: u8 = 4;
+const CLIENT_STATS_TOTAL: u8 = 5;
 
 const CLIENT_STATS_OP_NEXT: u8 = 0;
 const CLIENT_STATS_OP_PREV: u8 = 1;
@@ -142,14 +144,16 @@ impl Screen for DrmClientScreen
         ]).areas(main_area);
 
         let model = self.model.borrow();
-        let di = model.data.get_device(&self.sel.pci_dev).unwrap();
 
+        // device might have been hot-unplugged in the meantime
         let mut sel_cli: Option<&AppDataClientStats> = None;
-        for cli in di.clis_stats.iter() {
-            if cli.pid == self.sel.pid &&
-                cli.drm_minor == self.sel.drm_minor &&
-                cli.client_id == self.sel.client_id {
-                sel_cli = Some(cli);
+        if let Some(di) = model.data.get_device(&self.sel.pci_dev) {
+            for cli in di.clis_stats.iter() {
+                if cli.pid == self.sel.pid &&
+                    cli.drm_minor == self.sel.drm_minor &&
+                    cli.client_id == self.sel.client_id {
+                    sel_cli = Some(cli);
+                }
             }
         }
         if sel_cli.is_none() {
@@ -171,10 +175,13 @@ impl Screen for DrmClientScreen
         // render command scrollview
         self.render_command(sel_cli, frame, cmd_area);
 
-        // skip engines selection if no engines are known
+        // skip engines, freqs or power selection if they aren't known
         let mut stats_st = self.stats_state.borrow_mut();
-        if stats_st.sel == CLIENT_STATS_ENGINES &&
-            sel_cli.eng_stats.is_empty() {
+        let freq_engs = DrmClientScreen::freq_engines(sel_cli);
+        while (stats_st.sel == CLIENT_STATS_ENGINES &&
+            sel_cli.eng_stats.is_empty()) ||
+            (stats_st.sel == CLIENT_STATS_FREQS && freq_engs.is_empty()) ||
+            (stats_st.sel == CLIENT_STATS_POWER && sel_cli.power.is_empty()) {
             stats_st.repeat_op();
         }
         drop(stats_st);
@@ -212,6 +219,18 @@ impl Screen for DrmClientScreen
                 let mut st = self.stats_state.borrow_mut();
                 st.previous();
             },
+            KeyCode::Char('g') | KeyCode::Char('G') => {
+                let mut model = self.model.borrow_mut();
+                model.engs_grouped = !model.engs_grouped;
+            },
+            KeyCode::Char('n') | KeyCode::Char('N') => {
+                let mut model = self.model.borrow_mut();
+                model.engs_busiest = !model.engs_busiest;
+            },
+            KeyCode::Char('w') | KeyCode::Char('W') => {
+                let mut model = self.model.borrow_mut();
+                model.engs_freq_weighted = !model.engs_freq_weighted;
+            },
             _ => {}
         }
 
@@ -223,12 +242,22 @@ impl Screen for DrmClientScreen
         vec![
             " (←→) Scroll".magenta().bold(),
             " (< >) Change chart".light_yellow().bold(),
+            " (G/N/W) Engines view".white().bold(),
         ]
     }
 }
 
 impl DrmClientScreen
 {
+    // engines reporting their frequencies on DRM fdinfo
+    fn freq_engines(cli: &AppDataClientStats) -> Vec<&String>
+    {
+        cli.eng_stats.keys()
+            .filter(|en| cli.eng_stats.get(*en).unwrap().has_freqs())
+            .sorted()
+            .collect()
+    }
+
     fn render_command(&self,
         cli: &AppDataClientStats, frame: &mut Frame, area: Rect)
     {
@@ -259,6 +288,9 @@ impl DrmClientScreen
         cli: &AppDataClientStats, frame: &mut Frame, area: Rect)
     {
         let stats_st = self.stats_state.borrow();
+        let model = self.model.borrow();
+        let engs = model.engines_view(
+            &cli.eng_stats.keys().sorted().collect(), &cli.eng_stats);
 
         let [hdr_area, gauges_area] = Layout::vertical([
             Constraint::Length(1),
@@ -268,13 +300,20 @@ impl DrmClientScreen
         let mut widths = Vec::new();
         widths.push(Constraint::Length(12));   // SMEM
         widths.push(Constraint::Length(12));   // VRAM
-        for _ in cli.eng_stats.keys() {
+        for _ in engs.iter() {
             widths.push(Constraint::Fill(1));  // ENGINES
         }
+        let freq_engs = DrmClientScreen::freq_engines(cli);
+        if !freq_engs.is_empty() {
+            widths.push(Constraint::Length(10));  // FREQS
+        }
+        if !cli.power.is_empty() {
+            widths.push(Constraint::Length(8));   // POWER
+        }
         widths.push(Constraint::Length(7));    // CPU
 
         let gs_areas = Layout::horizontal(&widths).split(gauges_area);
-        let en_width = if !cli.eng_stats.is_empty() {
+        let en_width = if !engs.is_empty() {
             gs_areas[2].width as usize } else { 0 };
 
         // render headers
@@ -290,13 +329,26 @@ impl DrmClientScreen
             .alignment(Alignment::Center)
             .style(if stats_st.sel == CLIENT_STATS_MEMINFO {
                 ly_bold } else { wh_bold }));
-        for en in cli.eng_stats.keys().sorted() {
-            hdrs_lst.push(Line::from(en.to_uppercase())
+        for eng in engs.iter() {
+            let en = eng.label();
+            hdrs_lst.push(Line::from(en.clone())
                 .alignment(if en.len() > en_width {
                     Alignment::Left } else { Alignment::Center })
                 .style(if stats_st.sel == CLIENT_STATS_ENGINES {
                     ly_bold } else { wh_bold }));
         }
+        if !freq_engs.is_empty() {
+            hdrs_lst.push(Line::from("FREQS")
+                .alignment(Alignment::Center)
+                .style(if stats_st.sel == CLIENT_STATS_FREQS {
+                    ly_bold } else { wh_bold }));
+        }
+        if !cli.power.is_empty() {
+            hdrs_lst.push(Line::from("POWER")
+                .alignment(Alignment::Center)
+                .style(if stats_st.sel == CLIENT_STATS_POWER {
+                    ly_bold } else { wh_bold }));
+        }
         hdrs_lst.push(Line::from("CPU")
             .alignment(Alignment::Center)
             .style(if stats_st.sel == CLIENT_STATS_CPU {
@@ -324,22 +376,50 @@ impl DrmClientScreen
             Style::new().white());
         let vram_ratio = if mi.vram_used > 0 {
             mi.vram_rss as f64 / mi.vram_used as f64 } else { 0.0 };
-        stats_gs.push(App::gauge_colored_from(smem_label, smem_ratio));
-        stats_gs.push(App::gauge_colored_from(vram_label, vram_ratio));
+        stats_gs.push(App::gauge_colored_from(&model.theme,
+            smem_label, smem_ratio));
+        stats_gs.push(App::gauge_colored_from(&model.theme,
+            vram_label, vram_ratio));
 
-        for en in cli.eng_stats.keys().sorted() {
-            let eng = cli.eng_stats.get(en).unwrap();
+        for eng in engs.iter() {
             let eut = eng.usage.back().unwrap();  // always present
             let label = Span::styled(
                 format!("{:.1}%", eut), Style::new().white());
 
-            stats_gs.push(App::gauge_colored_from(label, eut/100.0));
+            stats_gs.push(App::gauge_colored_from(&model.theme,
+                label, eut/100.0));
+        }
+
+        // show the engine running closest to its max frequency
+        let fq_est = freq_engs.iter()
+            .filter_map(|en| cli.eng_stats.get(*en).unwrap().last_freqs())
+            .filter(|(_, mx)| *mx > 0)
+            .max_by(|(c1, m1), (c2, m2)| (*c1 as f64 / *m1 as f64)
+                .total_cmp(&(*c2 as f64 / *m2 as f64)));
+        if let Some((cur, mx)) = fq_est {
+            let fq_label = Span::styled(
+                format!("{}/{}", cur, mx), Style::new().white());
+            stats_gs.push(App::gauge_colored_from(&model.theme,
+                fq_label, cur as f64 / mx as f64));
+        }
+
+        // client's share of the device GPU power
+        if let Some(pwr) = cli.power.back() {
+            let dev_pwr = model.data.get_device(&self.sel.pci_dev)
+                .and_then(|di| di.dev_stats.power.back())
+                .map_or(0.0, |dp| dp.gpu_cur_power);
+            let pwr_label = Span::styled(
+                format!("{:.1}", pwr), Style::new().white());
+            let pwr_ratio = if dev_pwr > 0.0 { pwr / dev_pwr } else { 0.0 };
+            stats_gs.push(App::gauge_colored_from(&model.theme,
+                pwr_label, pwr_ratio));
         }
 
         let cpu = cli.cpu_usage.back().unwrap();  // always present
         let cpu_label = Span::styled(
             format!("{:.1}%", cpu), Style::new().white());
-        stats_gs.push(App::gauge_colored_from(cpu_label, cpu/100.0));
+        stats_gs.push(App::gauge_colored_from(&model.theme,
+            cpu_label, cpu/100.0));
 
         for (st_g, st_a) in stats_gs.iter().zip(gs_areas.iter()) {
             frame.render_widget(st_g, *st_a);
@@ -379,9 +459,18 @@ impl DrmClientScreen
             maxy = max(maxy, mi.smem_used);
             maxy = max(maxy, mi.vram_used);
         }
+        // growth rates over the leak detection window
+        let (sm_rate, vr_rate, leak) = if let Some(mg) = &cli.mem_growth {
+            (format!(" [{:+.1} MiB/min]", mg.smem_rate),
+                format!(" [{:+.1} MiB/min]", mg.vram_rate),
+                mg.is_leaking())
+        } else {
+            (String::new(), String::new(), false)
+        };
+
         let datasets = vec![
             Dataset::default()
-                .name("SMEM USED")
+                .name(format!("SMEM USED{}", sm_rate))
                 .marker(symbols::Marker::Braille)
                 .style(tailwind::BLUE.c700)
                 .graph_type(GraphType::Line)
@@ -393,7 +482,7 @@ impl DrmClientScreen
                 .graph_type(GraphType::Line)
                 .data(&sm_rss_vals),
             Dataset::default()
-                .name("VRAM USED")
+                .name(format!("VRAM USED{}", vr_rate))
                 .marker(symbols::Marker::Braille)
                 .style(tailwind::ORANGE.c700)
                 .graph_type(GraphType::Line)
@@ -413,7 +502,7 @@ impl DrmClientScreen
             Span::raw(format!("{}", App::short_mem_string(maxy))),
         ];
         let y_axis = Axis::default()
-            .title("Mem")
+            .title(if leak { "Mem (possible leak)" } else { "Mem" })
             .style(Style::new().white())
             .bounds(y_bounds)
             .labels(y_labels);
@@ -433,9 +522,11 @@ impl DrmClientScreen
         let mut eng_vals = Vec::new();
         let nr_vals = x_vals.len();
 
-        for en in cli.eng_stats.keys().sorted() {
+        let model = self.model.borrow();
+        let engs = model.engines_view(
+            &cli.eng_stats.keys().sorted().collect(), &cli.eng_stats);
+        for est in engs.iter() {
             let mut nlst = Vec::new();
-            let est = cli.eng_stats.get(en).unwrap();
 
             let mut idx = 0;
             if est.usage.len() < nr_vals {
@@ -454,9 +545,9 @@ impl DrmClientScreen
         let mut datasets = Vec::new();
         let mut color_idx = 1;
 
-        for (en, ed) in cli.eng_stats.keys().sorted().zip(eng_vals.iter()) {
+        for (eng, ed) in engs.iter().zip(eng_vals.iter()) {
             datasets.push(Dataset::default()
-                .name(en.to_uppercase())
+                .name(eng.label())
                 .marker(symbols::Marker::Braille)
                 .style(Color::Indexed(color_idx))
                 .graph_type(GraphType::Line)
@@ -471,7 +562,7 @@ impl DrmClientScreen
             Span::raw("100"),
         ];
         let y_axis = Axis::default()
-            .title("Usage (%)")
+            .title(model.engines_usage_title())
             .style(Style::new().white())
             .bounds(y_bounds)
             .labels(y_labels);
@@ -485,6 +576,127 @@ impl DrmClientScreen
             area);
     }
 
+    fn render_freqs_chart(&self, x_vals: &Vec<f64>, x_axis: Axis,
+        cli: &AppDataClientStats, frame: &mut Frame, area: Rect)
+    {
+        let mut freq_vals = Vec::new();
+        let nr_vals = x_vals.len();
+        let freq_engs = DrmClientScreen::freq_engines(cli);
+
+        let miny: u64 = 0;
+        let mut maxy: u64 = 1;
+
+        for en in freq_engs.iter() {
+            let mut nlst = Vec::new();
+            let est = cli.eng_stats.get(*en).unwrap();
+
+            let mut idx = 0;
+            if est.cur_freq.len() < nr_vals {
+                idx = nr_vals - est.cur_freq.len();
+                for i in 0..idx {
+                    nlst.push((x_vals[i], 0.0));
+                }
+            }
+            for i in idx..nr_vals {
+                let (cur, mx) = est.freqs(i-idx).unwrap_or((0, 0));
+                nlst.push((x_vals[i], cur as f64));
+                maxy = max(maxy, mx);
+            }
+
+            freq_vals.push(nlst);
+        }
+
+        let mut datasets = Vec::new();
+        let mut color_idx = 1;
+
+        for (en, fd) in freq_engs.iter().zip(freq_vals.iter()) {
+            let est = cli.eng_stats.get(*en).unwrap();
+            let name = if let Some((cur, mx)) = est.last_freqs() {
+                format!("{} {} MHz (max {})", en.to_uppercase(), cur, mx)
+            } else {
+                format!("{} N/A", en.to_uppercase())
+            };
+            datasets.push(Dataset::default()
+                .name(name)
+                .marker(symbols::Marker::Braille)
+                .style(Color::Indexed(color_idx))
+                .graph_type(GraphType::Line)
+                .data(fd));
+            color_idx += 1;
+        }
+
+        let y_bounds = [miny as f64, maxy as f64];
+        let y_labels = vec![
+            Span::raw(format!("{}", miny)),
+            Span::raw(format!("{}", (miny + maxy) / 2)),
+            Span::raw(format!("{}", maxy)),
+        ];
+        let y_axis = Axis::default()
+            .title("Freq (MHz)")
+            .style(Style::new().white())
+            .bounds(y_bounds)
+            .labels(y_labels);
+
+        frame.render_widget(Chart::new(datasets)
+            .x_axis(x_axis)
+            .y_axis(y_axis)
+            .legend_position(Some(LegendPosition::BottomLeft))
+            .hidden_legend_constraints((Constraint::Min(0), Constraint::Min(0)))
+            .style(Style::new().bold().on_black()),
+            area);
+    }
+
+    fn render_power_chart(&self, x_vals: &Vec<f64>, x_axis: Axis,
+        cli: &AppDataClientStats, frame: &mut Frame, area: Rect)
+    {
+        let mut pwr_vals = Vec::new();
+        let nr_vals = x_vals.len();
+
+        let miny: f64 = 0.0;
+        let mut maxy: f64 = 1.0;
+
+        let mut idx = 0;
+        if cli.power.len() < nr_vals {
+            idx = nr_vals - cli.power.len();
+            for i in 0..idx {
+                pwr_vals.push((x_vals[i], 0.0));
+            }
+        }
+        for i in idx..nr_vals {
+            pwr_vals.push((x_vals[i], cli.power[i-idx]));
+            maxy = maxy.max(cli.power[i-idx]);
+        }
+        let datasets = vec![
+            Dataset::default()
+                .name(format!("GPU {:.1} W ({:.1} J total)",
+                    cli.power.back().unwrap(), cli.energy))
+                .marker(symbols::Marker::Braille)
+                .style(tailwind::ORANGE.c700)
+                .graph_type(GraphType::Line)
+                .data(&pwr_vals),
+        ];
+
+        let y_bounds = [miny, maxy];
+        let y_labels = vec![
+            Span::raw(format!("{:.1}", miny)),
+            Span::raw(format!("{:.1}", (miny + maxy) / 2.0)),
+            Span::raw(format!("{:.1}", maxy)),
+        ];
+        let y_axis = Axis::default()
+            .title("Power (W)")
+            .style(Style::new().white())
+            .bounds(y_bounds)
+            .labels(y_labels);
+
+        frame.render_widget(Chart::new(datasets)
+            .x_axis(x_axis)
+            .y_axis(y_axis)
+            .legend_position(Some(LegendPosition::BottomLeft))
+            .hidden_legend_constraints((Constraint::Min(0), Constraint::Min(0)))
+            .style(Style::new().bold().on_black()),
+            area);
+    }
+
     fn render_cpu_chart(&self, x_vals: &Vec<f64>, x_axis: Axis,
         cli: &AppDataClientStats, frame: &mut Frame, area: Rect)
     {
@@ -575,6 +787,12 @@ impl DrmClientScreen
             CLIENT_STATS_ENGINES => {
                 self.render_engines_chart(&x_vals, x_axis, cli, frame, area);
             },
+            CLIENT_STATS_FREQS => {
+                self.render_freqs_chart(&x_vals, x_axis, cli, frame, area);
+            },
+            CLIENT_STATS_POWER => {
+                self.render_power_chart(&x_vals, x_axis, cli, frame, area);
+            },
             CLIENT_STATS_CPU => {
                 self.render_cpu_chart(&x_vals, x_axis, cli, frame, area);
             },
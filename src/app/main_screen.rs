@@ -1,5 +1,5 @@
 use std::cell::RefCell;
-use std::cmp::max;
+use std::cmp::{max, Reverse};
 use std::collections::VecDeque;
 use std::rc::Rc;
 
@@ -17,9 +17,12 @@ use ratatui::{
 };
 use tui_scrollview::{ScrollView, ScrollViewState, ScrollbarVisibility};
 
-use crate::app_data::{AppDataDeviceState, AppDataClientStats};
+use crate::app_data::{AppData, AppDataDeviceState, AppDataClientStats};
+use crate::app_config::AppConfig;
+use crate::drm_devices::DrmDeviceCapabilities;
 use crate::app::{App, AppModel, Screen, ScreenAction};
 use crate::app::drm_client_screen::{DrmClientScreen, DrmClientSelected};
+use crate::app::dev_details_screen::DevDetailsScreen;
 
 
 #[derive(Debug)]
@@ -55,6 +58,28 @@ impl DevicesTabState
         self.devs.is_empty()
     }
 
+    // returns true if the selected device changed
+    fn update(&mut self, devs: Vec<String>) -> bool
+    {
+        if devs == self.devs {
+            return false;
+        }
+
+        // keep the same device selected, if it's still around
+        let old_sel = self.devs.get(self.sel).cloned();
+        self.devs = devs;
+        if let Some(pos) = old_sel.as_ref()
+            .and_then(|od| self.devs.iter().position(|d| d == od)) {
+            self.sel = pos;
+            return false;
+        }
+        if self.sel >= self.devs.len() {
+            self.sel = if self.devs.is_empty() { 0 } else { self.devs.len() - 1 };
+        }
+
+        true
+    }
+
     fn new(devs: Vec<String>) -> DevicesTabState
     {
         DevicesTabState {
@@ -68,7 +93,10 @@ const DEVICE_STATS_FREQS: u8 = 0;
 const DEVICE_STATS_POWER: u8 = 1;
 const DEVICE_STATS_MEMINFO: u8 = 2;
 const DEVICE_STATS_ENGINES: u8 = 3;
-const DEVICE_STATS_TOTAL: u8 = 4;
+const DEVICE_STATS_TEMPS: u8 = 4;
+const DEVICE_STATS_FANS: u8 = 5;
+const DEVICE_STATS_THROTTLE: u8 = 6;
+const DEVICE_STATS_TOTAL: u8 = 7;
 
 const DEVICE_STATS_OP_NEXT: u8 = 0;
 const DEVICE_STATS_OP_PREV: u8 = 1;
@@ -104,10 +132,20 @@ impl DeviceStatsState
         }
     }
 
-    fn new() -> DeviceStatsState
+    fn new(chart: Option<&str>) -> DeviceStatsState
     {
+        let sel = match chart {
+            Some("power") => DEVICE_STATS_POWER,
+            Some("meminfo") => DEVICE_STATS_MEMINFO,
+            Some("engines") => DEVICE_STATS_ENGINES,
+            Some("temps") => DEVICE_STATS_TEMPS,
+            Some("fans") => DEVICE_STATS_FANS,
+            Some("throttle") => DEVICE_STATS_THROTTLE,
+            _ => DEVICE_STATS_FREQS,
+        };
+
         DeviceStatsState {
-            sel: DEVICE_STATS_FREQS,
+            sel,
             last_op: DEVICE_STATS_OP_NEXT,
         }
     }
@@ -185,19 +223,42 @@ impl Screen for MainScreen
 
     fn draw(&mut self, frame: &mut Frame, tab_area: Rect, main_area: Rect)
     {
-        // if not done yet, initialize tab state with devices
-        if self.tab_state.is_none() {
-            let model = self.model.borrow();
-            let mut dv: Vec<String> = Vec::new();
+        // initialize tab state with devices or update it with any
+        // hot-plugged/unplugged devices
+        let model = self.model.borrow();
+        let mut dv: Vec<String> = Vec::new();
 
-            if let Some(pdev) = &model.args.dev_slot {
-                dv.push(pdev.clone());
-            } else {
-                for di in model.data.devices() {
-                    dv.push(di.pci_dev.clone());
+        if let Some(pdev) = &model.args.dev_slot {
+            dv.push(pdev.clone());
+        } else {
+            // SR-IOV VFs are grouped right after their PF
+            let devs = model.data.devices();
+            let pf_of = |di: &AppDataDeviceState| {
+                di.sriov.as_ref().filter(|sr| sr.is_vf())
+                    .map(|sr| sr.pf_dev.clone())
+            };
+            for di in devs.iter() {
+                if let Some(pf) = pf_of(di) {
+                    if model.data.get_device(&pf).is_some() {
+                        continue;
+                    }
+                }
+                dv.push(di.pci_dev.clone());
+                for vdi in devs.iter() {
+                    if pf_of(vdi).as_ref() == Some(&di.pci_dev) {
+                        dv.push(vdi.pci_dev.clone());
+                    }
                 }
             }
+        }
+        drop(model);
 
+        if let Some(devs_ts) = &mut self.tab_state {
+            if devs_ts.update(dv) {
+                let mut st = self.clis_state.borrow_mut();
+                st.scroll_to_top();
+            }
+        } else {
             self.tab_state = Some(DevicesTabState::new(dv));
         }
 
@@ -213,7 +274,7 @@ impl Screen for MainScreen
         let model = self.model.borrow();
         let dn = &devs_ts.devs[devs_ts.sel];
         if let Some(dinfo) = model.data.get_device(dn) {
-            self.render_devs_tab(devs_ts, frame, tab_area);
+            self.render_devs_tab(&model.data, devs_ts, frame, tab_area);
             let tstamps = model.data.timestamps();
             self.render_drm_device(dinfo, tstamps, frame, main_area);
         } else {
@@ -272,6 +333,32 @@ impl Screen for MainScreen
                     return Some(ScreenAction::Enter(nscr));
                 }
             },
+            KeyCode::Char('g') | KeyCode::Char('G') => {
+                let mut model = self.model.borrow_mut();
+                model.engs_grouped = !model.engs_grouped;
+            },
+            KeyCode::Char('n') | KeyCode::Char('N') => {
+                let mut model = self.model.borrow_mut();
+                model.engs_busiest = !model.engs_busiest;
+            },
+            KeyCode::Char('w') | KeyCode::Char('W') => {
+                let mut model = self.model.borrow_mut();
+                model.engs_freq_weighted = !model.engs_freq_weighted;
+            },
+            KeyCode::Char('m') | KeyCode::Char('M') => {
+                let mut model = self.model.borrow_mut();
+                model.data.toggle_energy_mark();
+            },
+            KeyCode::Char('i') | KeyCode::Char('I') => {
+                if let Some(devs_ts) = &self.tab_state {
+                    if !devs_ts.is_empty() {
+                        let dn = devs_ts.devs[devs_ts.sel].clone();
+                        let nscr = DevDetailsScreen::new(
+                            self.model.clone(), dn);
+                        return Some(ScreenAction::Enter(nscr));
+                    }
+                }
+            },
             _ => {}
         }
 
@@ -285,44 +372,59 @@ impl Screen for MainScreen
             " (< >) Change chart".light_yellow().bold(),
             " (↑↓←→) Scroll".white().bold(),
             " (Enter) Select".white().bold(),
+            " (I) Info".white().bold(),
+            " (G/N/W) Engines view".white().bold(),
+            " (M) Mark energy".white().bold(),
         ]
     }
 }
 
 impl MainScreen
 {
-    fn client_pidmem(&self,
-        cli: &AppDataClientStats, widths: &Vec<Constraint>) -> Table
+    fn client_pidmem(&self, cfg: &AppConfig, cli: &AppDataClientStats,
+        caps: &DrmDeviceCapabilities, widths: &Vec<Constraint>) -> Table
     {
         let mem_info = cli.mem_info.back().unwrap();  // always present
+        let (smem_str, vram_str) = if caps.client_mem_info {
+            (App::short_mem_string(mem_info.smem_rss),
+                App::short_mem_string(mem_info.vram_rss))
+        } else {
+            (String::from("N/A"), String::from("N/A"))
+        };
 
-        let rows = [Row::new([
-                Line::from(cli.pid.to_string())
-                    .alignment(Alignment::Center),
-                Line::from(App::short_mem_string(mem_info.smem_rss))
-                    .alignment(Alignment::Center),
-                Line::from(App::short_mem_string(mem_info.vram_rss))
-                    .alignment(Alignment::Center),
-                Line::from(cli.drm_minor.to_string())
-                    .alignment(Alignment::Center),
-        ])];
+        let mut cells = vec![Line::from(cli.pid.to_string())
+            .alignment(Alignment::Center)];
+        if cfg.is_column_visible("smem") {
+            cells.push(Line::from(smem_str).alignment(Alignment::Center));
+        }
+        if cfg.is_column_visible("vram") {
+            cells.push(Line::from(vram_str).alignment(Alignment::Center));
+        }
+        if cfg.is_column_visible("min") {
+            cells.push(Line::from(cli.drm_minor.to_string())
+                .alignment(Alignment::Center));
+        }
+        let rows = [Row::new(cells)];
 
         Table::new(rows, widths)
             .column_spacing(1)
             .style(Style::new().white())
     }
 
-    fn render_client_engines(&self, cli: &AppDataClientStats,
-        constrs: &Vec<Constraint>, clis_sv: &mut ScrollView, area: Rect)
+    fn render_client_engines(&self, model: &AppModel,
+        cli: &AppDataClientStats, constrs: &Vec<Constraint>,
+        clis_sv: &mut ScrollView, area: Rect)
     {
         let mut gauges: Vec<Gauge> = Vec::new();
-        for en in cli.eng_stats.keys().sorted() {
-            let eng = cli.eng_stats.get(en).unwrap();
+        let engs = model.engines_view(
+            &cli.eng_stats.keys().sorted().collect(), &cli.eng_stats);
+        for eng in engs.iter() {
             let eut = eng.usage.back().unwrap();  // always present
             let label = Span::styled(
                 format!("{:.1}%", eut), Style::new().white());
 
-            gauges.push(App::gauge_colored_from(label, eut/100.0));
+            gauges.push(App::gauge_colored_from(&model.theme,
+                label, eut/100.0));
         }
         let places = Layout::horizontal(constrs).split(area);
 
@@ -331,29 +433,73 @@ impl MainScreen
         }
     }
 
-    fn client_cpu_usage(&self, cli: &AppDataClientStats) -> Gauge
+    fn client_cpu_usage(&self,
+        model: &AppModel, cli: &AppDataClientStats) -> Gauge
     {
         let cpu = cli.cpu_usage.back().unwrap();  // always present
         let label = Span::styled(
             format!("{:.1}%", cpu), Style::new().white());
 
-        App::gauge_colored_from(label, cpu/100.0)
+        App::gauge_colored_from(&model.theme, label, cpu/100.0)
+    }
+
+    fn client_power(&self,
+        cli: &AppDataClientStats, caps: &DrmDeviceCapabilities) -> Line<'static>
+    {
+        let pwr_str = if caps.power {
+            format!("{:.1}", cli.power.back().unwrap_or(&0.0))
+        } else {
+            String::from("N/A")
+        };
+
+        Line::from(pwr_str)
+            .alignment(Alignment::Center)
+            .style(Style::new().white())
     }
 
     fn client_cmd(&self, cli: &AppDataClientStats) -> Line
     {
-        Line::from(format!("[{}] {}", &cli.comm, &cli.cmdline))
+        let mut spans = Vec::new();
+        if cli.mem_growth.as_ref().is_some_and(|mg| mg.is_leaking()) {
+            spans.push("LEAK? ".light_red().bold());
+        }
+        spans.push(Span::raw(format!("[{}] {}", &cli.comm, &cli.cmdline)));
+
+        Line::from(spans)
             .alignment(Alignment::Left)
             .style(Style::new().white())
     }
 
+    // usage and memory from highest, pid and command in ascending order
+    fn sort_clients(sort: &str, cinfos: &mut Vec<&AppDataClientStats>)
+    {
+        let last = |vals: &VecDeque<f64>| *vals.back().unwrap_or(&0.0);
+        let engs = |cli: &AppDataClientStats| cli.eng_stats.values()
+            .map(|est| last(&est.usage)).sum::<f64>();
+
+        match sort {
+            "pid" => cinfos.sort_by_key(|cli| cli.pid),
+            "smem" => cinfos.sort_by_key(|cli| Reverse(
+                cli.mem_info.back().map_or(0, |mi| mi.smem_rss))),
+            "vram" => cinfos.sort_by_key(|cli| Reverse(
+                cli.mem_info.back().map_or(0, |mi| mi.vram_rss))),
+            "engines" => cinfos.sort_by(|a, b| engs(b).total_cmp(&engs(a))),
+            "cpu" => cinfos.sort_by(|a, b|
+                last(&b.cpu_usage).total_cmp(&last(&a.cpu_usage))),
+            "power" => cinfos.sort_by(|a, b|
+                last(&b.power).total_cmp(&last(&a.power))),
+            "command" => cinfos.sort_by(|a, b| a.comm.cmp(&b.comm)),
+            _ => {}
+        }
+    }
+
     fn render_drm_clients(&self,
         dinfo: &AppDataDeviceState, frame: &mut Frame, visible_area: Rect)
     {
         // get all client info and create scrollviews with right size
         let mut cinfos: Vec<&AppDataClientStats> = Vec::new();
         let mut constrs = Vec::new();
-        let mut clis_sv_w = max(90, visible_area.width);
+        let mut clis_sv_w = max(97, visible_area.width);
         let mut clis_sv_h: u16 = 0;
 
         let model = self.model.borrow();
@@ -362,10 +508,13 @@ impl MainScreen
                 cinfos.push(cli);
                 constrs.push(Constraint::Length(1));
                 clis_sv_w = max(clis_sv_w,
-                    (80 + cli.comm.len() + cli.cmdline.len() + 3) as u16);
+                    (87 + cli.comm.len() + cli.cmdline.len() + 3) as u16);
                 clis_sv_h += 1;
            }
         }
+        if let Some(sort) = &model.config.sort {
+            MainScreen::sort_clients(sort, &mut cinfos);
+        }
 
         let mut hdr_sv = ScrollView::new(Size::new(clis_sv_w, 1))
             .scrollbars_visibility(ScrollbarVisibility::Never);
@@ -407,29 +556,35 @@ impl MainScreen
                 .borders(Borders::NONE)
                 .style(Style::new().on_dark_gray()),
                 hdr_sv_area);
+        // PID and COMMAND are always shown, other columns can be hidden
+        let cfg = &model.config;
+        let mut texts = vec![Line::from("PID").alignment(Alignment::Center)];
+        let mut pidmem_widths = vec![Constraint::Max(6)];
+        let mut pidmem_w = 6;
+        for (col, hdr, w) in [("smem", "SMEM", 5), ("vram", "VRAM", 5),
+            ("min", "MIN", 3)] {
+            if cfg.is_column_visible(col) {
+                texts.push(Line::from(hdr).alignment(Alignment::Center));
+                pidmem_widths.push(Constraint::Max(w));
+                pidmem_w += w + 1;
+            }
+        }
+        let show_engs = cfg.is_column_visible("engines");
+        let show_cpu = cfg.is_column_visible("cpu");
+        let show_pwr = cfg.is_column_visible("power");
+
         let line_widths = vec![
-            Constraint::Max(22),
+            Constraint::Max(pidmem_w),
             Constraint::Length(1),
-            Constraint::Max(42),
-            Constraint::Max(7),
+            if show_engs { Constraint::Max(42) } else { Constraint::Length(0) },
+            if show_cpu { Constraint::Max(7) } else { Constraint::Length(0) },
+            if show_pwr { Constraint::Max(7) } else { Constraint::Length(0) },
             Constraint::Length(1),
             Constraint::Min(5),
         ];
-        let [pidmem_hdr, _, engines_hdr, cpu_hdr, _, cmd_hdr] =
+        let [pidmem_hdr, _, engines_hdr, cpu_hdr, pwr_hdr, _, cmd_hdr] =
             Layout::horizontal(&line_widths).areas(hdr_sv_area);
 
-        let texts = vec![
-            Line::from("PID").alignment(Alignment::Center),
-            Line::from("SMEM").alignment(Alignment::Center),
-            Line::from("VRAM").alignment(Alignment::Center),
-            Line::from("MIN").alignment(Alignment::Center),
-        ];
-        let pidmem_widths = vec![
-            Constraint::Max(6),
-            Constraint::Max(5),
-            Constraint::Max(5),
-            Constraint::Max(3),
-        ];
         hdr_sv.render_widget(Table::new([Row::new(texts)], &pidmem_widths)
             .column_spacing(1)
             .block(Block::new()
@@ -439,10 +594,13 @@ impl MainScreen
 
         let mut texts = Vec::new();
         let mut eng_widths = Vec::new();
-        let en_width = if !dinfo.eng_names.is_empty() {
-            engines_hdr.width as usize / dinfo.eng_names.len() } else { 0 };
-        for en in dinfo.eng_names.iter() {
-            texts.push(Line::from(en.to_uppercase())
+        let engs = model.engines_view(
+            &dinfo.eng_names.iter().collect(), &dinfo.dev_stats.eng_stats);
+        let en_width = if !engs.is_empty() {
+            engines_hdr.width as usize / engs.len() } else { 0 };
+        for eng in engs.iter() {
+            let en = eng.label();
+            texts.push(Line::from(en.clone())
                 .alignment(if en.len() > en_width {
                     Alignment::Left } else { Alignment::Center }));
             eng_widths.push(Constraint::Fill(1));
@@ -454,10 +612,18 @@ impl MainScreen
                 .style(Style::new().white().bold().on_dark_gray())),
             engines_hdr);
 
-        hdr_sv.render_widget(Line::from("CPU")
-            .alignment(Alignment::Center)
-            .style(Style::new().white().bold().on_dark_gray()),
-            cpu_hdr);
+        if show_cpu {
+            hdr_sv.render_widget(Line::from("CPU")
+                .alignment(Alignment::Center)
+                .style(Style::new().white().bold().on_dark_gray()),
+                cpu_hdr);
+        }
+        if show_pwr {
+            hdr_sv.render_widget(Line::from("POWER")
+                .alignment(Alignment::Center)
+                .style(Style::new().white().bold().on_dark_gray()),
+                pwr_hdr);
+        }
         hdr_sv.render_widget(Line::from("COMMAND")
             .alignment(Alignment::Left)
             .style(Style::new().white().bold().on_dark_gray()),
@@ -476,17 +642,27 @@ impl MainScreen
                if row_nr == state.sel_row {
                     clis_sv.render_widget(Block::new()
                         .borders(Borders::NONE)
-                        .style(Style::new().on_light_blue()),
+                        .style(Style::new().bg(model.theme.selected)),
                         *area);
                 }
-                let [pidmem_area, _, engines_area, cpu_area, _, cmd_area] =
+                let [pidmem_area, _, engines_area, cpu_area,
+                    pwr_area, _, cmd_area] =
                     Layout::horizontal(&line_widths).areas(*area);
 
-                clis_sv.render_widget(
-                    self.client_pidmem(cli, &pidmem_widths), pidmem_area);
-                self.render_client_engines(
-                    cli, &eng_widths, &mut clis_sv, engines_area);
-                clis_sv.render_widget(self.client_cpu_usage(cli), cpu_area);
+                clis_sv.render_widget(self.client_pidmem(
+                    cfg, cli, &dinfo.caps, &pidmem_widths), pidmem_area);
+                if show_engs {
+                    self.render_client_engines(
+                        &model, cli, &eng_widths, &mut clis_sv, engines_area);
+                }
+                if show_cpu {
+                    clis_sv.render_widget(
+                        self.client_cpu_usage(&model, cli), cpu_area);
+                }
+                if show_pwr {
+                    clis_sv.render_widget(
+                        self.client_power(cli, &dinfo.caps), pwr_area);
+                }
                 clis_sv.render_widget(self.client_cmd(cli), cmd_area);
 
                 row_nr += 1;
@@ -556,9 +732,11 @@ impl MainScreen
         let mut eng_vals = Vec::new();
         let nr_vals = x_vals.len();
 
-        for en in dinfo.eng_names.iter() {
+        let model = self.model.borrow();
+        let engs = model.engines_view(
+            &dinfo.eng_names.iter().collect(), &dinfo.dev_stats.eng_stats);
+        for est in engs.iter() {
             let mut nlst = Vec::new();
-            let est = dinfo.dev_stats.eng_stats.get(en).unwrap();
 
             let mut idx = 0;
             if est.usage.len() < nr_vals {
@@ -577,9 +755,9 @@ impl MainScreen
         let mut datasets = Vec::new();
         let mut color_idx = 1;
 
-        for (en, ed) in dinfo.eng_names.iter().zip(eng_vals.iter()) {
+        for (eng, ed) in engs.iter().zip(eng_vals.iter()) {
             datasets.push(Dataset::default()
-                .name(en.to_uppercase())
+                .name(eng.label())
                 .marker(symbols::Marker::Braille)
                 .style(Color::Indexed(color_idx))
                 .graph_type(GraphType::Line)
@@ -594,7 +772,7 @@ impl MainScreen
             Span::raw("100"),
         ];
         let y_axis = Axis::default()
-            .title("Usage (%)")
+            .title(model.engines_usage_title())
             .style(Style::new().white())
             .bounds(y_bounds)
             .labels(y_labels);
@@ -622,25 +800,65 @@ impl MainScreen
             gpu_vals.push((*xval, pwr.gpu_cur_power));
             pkg_vals.push((*xval, pwr.pkg_cur_power));
         }
+        for pl in dinfo.power_limits.iter() {
+            maxy = f64::max(maxy, pl.limit);
+        }
         if maxy == 0.0 {
             maxy = 100.0;
         }
 
-        let datasets = vec![
+        // energy since start and over the marked interval (if any)
+        let (pkg_name, gpu_name) = if let Some(nrg) = &dinfo.dev_stats.energy {
+            let mark_str = |val: f64| if nrg.mark_start.is_some() {
+                format!(" | {}: {}",
+                    if nrg.is_marking() { "marking" } else { "marked" },
+                    App::energy_string(val))
+            } else {
+                String::new()
+            };
+            (format!("PKG [{}{}]", App::energy_string(nrg.pkg_energy),
+                    mark_str(nrg.mark_pkg_energy)),
+                format!("GPU [{}{}]", App::energy_string(nrg.gpu_energy),
+                    mark_str(nrg.mark_gpu_energy)))
+        } else {
+            (String::from("PKG"), String::from("GPU"))
+        };
+
+        let mut datasets = vec![
             Dataset::default()
-                .name("PKG")
+                .name(pkg_name)
                 .marker(symbols::Marker::Braille)
                 .style(tailwind::BLUE.c700)
                 .graph_type(GraphType::Line)
                 .data(&pkg_vals),
             Dataset::default()
-                .name("GPU")
+                .name(gpu_name)
                 .marker(symbols::Marker::Braille)
                 .style(tailwind::GREEN.c700)
                 .graph_type(GraphType::Line)
                 .data(&gpu_vals),
         ];
 
+        // draw power limits as horizontal lines across the whole chart
+        let lim_colors = [
+            tailwind::RED.c700, tailwind::ORANGE.c700, tailwind::YELLOW.c700,
+            tailwind::PURPLE.c700, tailwind::PINK.c700, tailwind::CYAN.c700,
+        ];
+        let x_first = *x_vals.first().unwrap();  // always present
+        let x_last = *x_vals.last().unwrap();
+        let lim_vals: Vec<_> = dinfo.power_limits.iter()
+            .map(|pl| vec![(x_first, pl.limit), (x_last, pl.limit)])
+            .collect();
+        for (idx, (pl, lv)) in dinfo.power_limits.iter()
+            .zip(lim_vals.iter()).enumerate() {
+            datasets.push(Dataset::default()
+                .name(format!("{} [{:.1}]", pl.name, pl.limit))
+                .marker(symbols::Marker::Braille)
+                .style(lim_colors[idx % lim_colors.len()])
+                .graph_type(GraphType::Line)
+                .data(lv));
+        }
+
         let y_bounds = [miny, maxy];
         let y_labels = vec![
             Span::raw(format!("{:.1}", miny)),
@@ -662,35 +880,163 @@ impl MainScreen
             area);
     }
 
+    fn render_temps_chart(&self, x_vals: &Vec<f64>, x_axis: Axis,
+        dinfo: &AppDataDeviceState, frame: &mut Frame, area: Rect)
+    {
+        let last = dinfo.dev_stats.temps.back().unwrap();  // always present
+        let mut temp_vals = Vec::new();
+        let mut maxy: f64 = 0.0;
+        let miny = 0.0;
+
+        for tp in last.iter() {
+            let mut nlst = Vec::new();
+            for (tps, xval) in dinfo.dev_stats.temps.iter().zip(x_vals.iter()) {
+                if let Some(t) = tps.iter().find(|t| t.name == tp.name) {
+                    maxy = f64::max(maxy, t.temp);
+                    nlst.push((*xval, t.temp));
+                }
+            }
+            maxy = f64::max(maxy, tp.limit());
+            temp_vals.push(nlst);
+        }
+        if maxy == 0.0 {
+            maxy = 100.0;
+        }
+
+        let mut datasets = Vec::new();
+        let mut color_idx = 1;
+
+        for (tp, td) in last.iter().zip(temp_vals.iter()) {
+            datasets.push(Dataset::default()
+                .name(format!("{} [{:.0}]", tp.name.to_uppercase(), tp.temp))
+                .marker(symbols::Marker::Braille)
+                .style(Color::Indexed(color_idx))
+                .graph_type(GraphType::Line)
+                .data(td));
+            color_idx += 1;
+        }
+
+        let y_bounds = [miny, maxy];
+        let y_labels = vec![
+            Span::raw(format!("{:.0}", miny)),
+            Span::raw(format!("{:.0}", (miny + maxy) / 2.0)),
+            Span::raw(format!("{:.0}", maxy)),
+        ];
+        let y_axis = Axis::default()
+            .title("Temp (°C)")
+            .style(Style::new().white())
+            .bounds(y_bounds)
+            .labels(y_labels);
+
+        frame.render_widget(Chart::new(datasets)
+            .x_axis(x_axis)
+            .y_axis(y_axis)
+            .legend_position(Some(LegendPosition::BottomLeft))
+            .hidden_legend_constraints((Constraint::Min(0), Constraint::Min(0)))
+            .style(Style::new().bold().on_black()),
+            area);
+    }
+
+    fn render_fans_chart(&self, x_vals: &Vec<f64>, x_axis: Axis,
+        dinfo: &AppDataDeviceState, frame: &mut Frame, area: Rect)
+    {
+        let last = dinfo.dev_stats.fans.back().unwrap();  // always present
+        let mut fan_vals = Vec::new();
+        let mut maxy: f64 = 0.0;
+        let miny = 0.0;
+
+        for fan in last.iter() {
+            let mut nlst = Vec::new();
+            for (fns, xval) in dinfo.dev_stats.fans.iter().zip(x_vals.iter()) {
+                if let Some(f) = fns.iter().find(|f| f.name == fan.name) {
+                    maxy = f64::max(maxy, f.speed as f64);
+                    nlst.push((*xval, f.speed as f64));
+                }
+            }
+            maxy = f64::max(maxy, fan.max as f64);
+            fan_vals.push(nlst);
+        }
+        if maxy == 0.0 {
+            maxy = 1000.0;
+        }
+
+        let mut datasets = Vec::new();
+        let mut color_idx = 1;
+
+        for (fan, fd) in last.iter().zip(fan_vals.iter()) {
+            let name = if fan.duty > 0.0 {
+                format!("{} [{} @ {:.0}%]",
+                    fan.name.to_uppercase(), fan.speed, fan.duty)
+            } else {
+                format!("{} [{}]", fan.name.to_uppercase(), fan.speed)
+            };
+            datasets.push(Dataset::default()
+                .name(name)
+                .marker(symbols::Marker::Braille)
+                .style(Color::Indexed(color_idx))
+                .graph_type(GraphType::Line)
+                .data(fd));
+            color_idx += 1;
+        }
+
+        let y_bounds = [miny, maxy];
+        let y_labels = vec![
+            Span::raw(format!("{:.0}", miny)),
+            Span::raw(format!("{:.0}", (miny + maxy) / 2.0)),
+            Span::raw(format!("{:.0}", maxy)),
+        ];
+        let y_axis = Axis::default()
+            .title("Fan (RPM)")
+            .style(Style::new().white())
+            .bounds(y_bounds)
+            .labels(y_labels);
+
+        frame.render_widget(Chart::new(datasets)
+            .x_axis(x_axis)
+            .y_axis(y_axis)
+            .legend_position(Some(LegendPosition::BottomLeft))
+            .hidden_legend_constraints((Constraint::Min(0), Constraint::Min(0)))
+            .style(Style::new().bold().on_black()),
+            area);
+    }
+
     fn render_freqs_chart(&self, x_vals: &Vec<f64>, x_axis: Axis,
         dinfo: &AppDataDeviceState, frame: &mut Frame, area: Rect)
     {
         let mut cur_freq_ds = Vec::new();
         let mut act_freq_ds = Vec::new();
+        let mut mem_freq_ds = Vec::new();
         let mut tr_pl1 = Vec::new();
         let mut tr_status = Vec::new();
 
-        let miny = dinfo.freq_limits.minimum as f64;
-        let maxy = dinfo.freq_limits.maximum as f64;
+        let fls = &dinfo.freq_limits;
+        let has_mem = fls.mem_maximum > 0 ||
+            dinfo.dev_stats.freqs.iter().any(|fqs| fqs.mem_freq > 0);
+        let mut miny = fls.minimum as f64;
+        let mut maxy = fls.maximum as f64;
+        if has_mem {
+            if fls.mem_minimum > 0 {
+                miny = f64::min(miny, fls.mem_minimum as f64);
+            }
+            maxy = f64::max(maxy, fls.mem_maximum as f64);
+        }
 
         for (fqs, xval) in dinfo.dev_stats.freqs.iter().zip(x_vals.iter()) {
             cur_freq_ds.push((*xval, fqs.cur_freq as f64));
             act_freq_ds.push((*xval, fqs.act_freq as f64));
+            mem_freq_ds.push((*xval, fqs.mem_freq as f64));
 
+            // only the throttled samples, as scattered points
             if fqs.throttle_reasons.pl1 {
                 tr_pl1.push((*xval, (miny + maxy) / 2.0));
-            } else {
-                tr_pl1.push((*xval, -1.0));  // hide it
             }
             if fqs.throttle_reasons.status {
                 tr_status.push((*xval, (miny + maxy) / 2.0));
-            } else {
-                tr_status.push((*xval, -1.0));  // hide it
             }
         }
         let fq = dinfo.dev_stats.freqs.back().unwrap();  // always present
 
-        let datasets = vec![
+        let mut datasets = vec![
             Dataset::default()
                 .name(format!("Requested [{}]", fq.cur_freq))
                 .marker(symbols::Marker::Braille)
@@ -703,19 +1049,27 @@ impl MainScreen
                 .style(tailwind::GREEN.c700)
                 .graph_type(GraphType::Line)
                 .data(&act_freq_ds),
-            Dataset::default()
-                .name("Throttle: Status")
-                .marker(symbols::Marker::Braille)
-                .style(tailwind::ORANGE.c700)
-                .graph_type(GraphType::Line)
-                .data(&tr_status),
-            Dataset::default()
-                .name("Throttle: PL1")
+        ];
+        if has_mem {
+            datasets.push(Dataset::default()
+                .name(format!("Memory    [{}]", fq.mem_freq))
                 .marker(symbols::Marker::Braille)
-                .style(tailwind::RED.c700)
+                .style(tailwind::PURPLE.c700)
                 .graph_type(GraphType::Line)
-                .data(&tr_pl1),
-        ];
+                .data(&mem_freq_ds));
+        }
+        datasets.push(Dataset::default()
+            .name("Throttle: Status")
+            .marker(symbols::Marker::Braille)
+            .style(tailwind::ORANGE.c700)
+            .graph_type(GraphType::Scatter)
+            .data(&tr_status));
+        datasets.push(Dataset::default()
+            .name("Throttle: PL1")
+            .marker(symbols::Marker::Braille)
+            .style(tailwind::RED.c700)
+            .graph_type(GraphType::Scatter)
+            .data(&tr_pl1));
 
         let y_bounds = [miny, maxy];
         let y_labels = vec![
@@ -738,6 +1092,60 @@ impl MainScreen
             area);
     }
 
+    fn render_throttle_chart(&self, x_vals: &Vec<f64>, x_axis: Axis,
+        dinfo: &AppDataDeviceState, frame: &mut Frame, area: Rect)
+    {
+        let fq = dinfo.dev_stats.freqs.back().unwrap();  // always present
+        let reasons = fq.throttle_reasons.reasons();
+        let nr_reasons = reasons.len();
+        let mut tr_vals = Vec::new();
+
+        // one lane per throttle reason, from the top
+        for (idx, (name, _)) in reasons.iter().enumerate() {
+            let lane = (nr_reasons - idx) as f64 - 0.5;
+            let mut nlst = Vec::new();
+            for (fqs, xval) in dinfo.dev_stats.freqs.iter().zip(x_vals.iter()) {
+                let active = fqs.throttle_reasons.reasons().iter()
+                    .any(|(rn, act)| rn == name && *act);
+                if active {
+                    nlst.push((*xval, lane));
+                }
+            }
+            tr_vals.push(nlst);
+        }
+
+        let mut datasets = Vec::new();
+        let mut color_idx = 1;
+
+        for ((name, _), td) in reasons.iter().zip(tr_vals.iter()) {
+            let residency = dinfo.dev_stats.throttle.as_ref()
+                .and_then(|thr| thr.reasons.iter().find(|r| r.name == *name))
+                .map_or(0.0, |r| r.residency);
+            datasets.push(Dataset::default()
+                .name(format!("{:<13} [{:.1}%]",
+                    name.to_uppercase(), residency))
+                .marker(symbols::Marker::Braille)
+                .style(Color::Indexed(color_idx))
+                .graph_type(GraphType::Scatter)
+                .data(td));
+            color_idx += 1;
+        }
+
+        let y_bounds = [0.0, nr_reasons as f64];
+        let y_axis = Axis::default()
+            .title("Throttle reasons (% of session)")
+            .style(Style::new().white())
+            .bounds(y_bounds);
+
+        frame.render_widget(Chart::new(datasets)
+            .x_axis(x_axis)
+            .y_axis(y_axis)
+            .legend_position(Some(LegendPosition::BottomLeft))
+            .hidden_legend_constraints((Constraint::Min(0), Constraint::Min(0)))
+            .style(Style::new().bold().on_black()),
+            area);
+    }
+
     fn render_dev_stats(&self, dinfo: &AppDataDeviceState,
         tstamps: &VecDeque<u128>, frame: &mut Frame, area: Rect)
     {
@@ -773,8 +1181,23 @@ impl MainScreen
             .column_spacing(1),
             inf_area);
 
+        // skip charts for stats the driver can't provide
+        let caps = &dinfo.caps;
+        let no_temps = Vec::new();
+        let temps = dinfo.dev_stats.temps.back().unwrap_or(&no_temps);
+        let no_fans = Vec::new();
+        let fans = dinfo.dev_stats.fans.back().unwrap_or(&no_fans);
+        let model = self.model.borrow();
+        let engs = model.engines_view(
+            &dinfo.eng_names.iter().collect(), &dinfo.dev_stats.eng_stats);
         let mut ds_st = self.dstats_state.borrow_mut();
-        if ds_st.sel == DEVICE_STATS_ENGINES && dinfo.eng_names.is_empty() {
+        while ((ds_st.sel == DEVICE_STATS_FREQS ||
+            ds_st.sel == DEVICE_STATS_THROTTLE) && !caps.freqs) ||
+            (ds_st.sel == DEVICE_STATS_POWER && !caps.power) ||
+            (ds_st.sel == DEVICE_STATS_ENGINES &&
+            dinfo.eng_names.is_empty()) ||
+            (ds_st.sel == DEVICE_STATS_TEMPS && temps.is_empty()) ||
+            (ds_st.sel == DEVICE_STATS_FANS && fans.is_empty()) {
             ds_st.repeat_op();
         }
 
@@ -786,15 +1209,21 @@ impl MainScreen
         let mut dstats_widths = Vec::new();
         dstats_widths.push(Constraint::Length(12));   // SMEM
         dstats_widths.push(Constraint::Length(12));   // VRAM
-        for _ in dinfo.eng_names.iter() {
+        for _ in engs.iter() {
             dstats_widths.push(Constraint::Fill(1));  // ENGINES
         }
         dstats_widths.push(Constraint::Length(10));   // FREQS
         dstats_widths.push(Constraint::Length(12));   // POWER
+        if !temps.is_empty() {
+            dstats_widths.push(Constraint::Length(8));  // TEMP
+        }
+        if !fans.is_empty() {
+            dstats_widths.push(Constraint::Length(8));  // FAN
+        }
 
         // split area for gauges early to calculate max engine name length
         let gs_areas = Layout::horizontal(&dstats_widths).split(gauges_area);
-        let en_width = if !dinfo.eng_names.is_empty() {
+        let en_width = if !engs.is_empty() {
             gs_areas[2].width as usize } else { 0 };
 
         let mut hdrs_lst = Vec::new();
@@ -809,8 +1238,9 @@ impl MainScreen
             .alignment(Alignment::Center)
             .style(if ds_st.sel == DEVICE_STATS_MEMINFO {
                 ly_bold } else { wh_bold }));
-        for en in dinfo.eng_names.iter() {
-            hdrs_lst.push(Line::from(en.to_uppercase())
+        for eng in engs.iter() {
+            let en = eng.label();
+            hdrs_lst.push(Line::from(en.clone())
                 .alignment(if en.len() > en_width {
                     Alignment::Left } else { Alignment::Center })
                 .style(if ds_st.sel == DEVICE_STATS_ENGINES {
@@ -818,12 +1248,25 @@ impl MainScreen
         }
         hdrs_lst.push(Line::from("FREQS")
             .alignment(Alignment::Center)
-            .style(if ds_st.sel == DEVICE_STATS_FREQS {
+            .style(if ds_st.sel == DEVICE_STATS_FREQS ||
+                ds_st.sel == DEVICE_STATS_THROTTLE {
                 ly_bold } else { wh_bold }));
         hdrs_lst.push(Line::from("POWER")
             .alignment(Alignment::Center)
             .style(if ds_st.sel == DEVICE_STATS_POWER {
                 ly_bold } else { wh_bold }));
+        if !temps.is_empty() {
+            hdrs_lst.push(Line::from("TEMP")
+                .alignment(Alignment::Center)
+                .style(if ds_st.sel == DEVICE_STATS_TEMPS {
+                    ly_bold } else { wh_bold }));
+        }
+        if !fans.is_empty() {
+            hdrs_lst.push(Line::from("FAN")
+                .alignment(Alignment::Center)
+                .style(if ds_st.sel == DEVICE_STATS_FANS {
+                    ly_bold } else { wh_bold }));
+        }
 
         let dstats_hdr = [Row::new(hdrs_lst)];
         frame.render_widget(Table::new(dstats_hdr, &dstats_widths)
@@ -846,33 +1289,77 @@ impl MainScreen
             Style::new().white());
         let vram_ratio = if mi.vram_total > 0 {
             mi.vram_used as f64 / mi.vram_total as f64 } else { 0.0 };
-        dstats_gs.push(App::gauge_colored_from(smem_label, smem_ratio));
-        dstats_gs.push(App::gauge_colored_from(vram_label, vram_ratio));
-
-        for en in dinfo.eng_names.iter() {
-            let eng = dinfo.dev_stats.eng_stats.get(en).unwrap();
+        dstats_gs.push(if caps.smem_used {
+            App::gauge_colored_from(&model.theme,
+                smem_label, smem_ratio) } else {
+            App::gauge_not_available() });
+        dstats_gs.push(if caps.vram {
+            App::gauge_colored_from(&model.theme,
+                vram_label, vram_ratio) } else {
+            App::gauge_not_available() });
+
+        for eng in engs.iter() {
             let eut = eng.usage.back().unwrap();  // always present
             let label = Span::styled(
                 format!("{:.1}%", eut), Style::new().white());
 
-            dstats_gs.push(App::gauge_colored_from(label, eut/100.0));
+            dstats_gs.push(App::gauge_colored_from(&model.theme,
+                label, eut/100.0));
         }
 
-        let freqs = dinfo.dev_stats.freqs.back().unwrap();  // always present
-        let freqs_label = Span::styled(
-            format!("{}/{}", freqs.act_freq, freqs.cur_freq),
-            Style::new().white());
-        let freqs_ratio = if freqs.cur_freq > 0 {
-            freqs.act_freq as f64 / freqs.cur_freq as f64 } else { 0.0 };
-        dstats_gs.push(App::gauge_colored_from(freqs_label, freqs_ratio));
+        if let Some(freqs) = dinfo.dev_stats.freqs.back() {
+            let freqs_label = Span::styled(
+                format!("{}/{}", freqs.act_freq, freqs.cur_freq),
+                Style::new().white());
+            let freqs_ratio = if freqs.cur_freq > 0 {
+                freqs.act_freq as f64 / freqs.cur_freq as f64 } else { 0.0 };
+            dstats_gs.push(App::gauge_colored_from(&model.theme,
+                freqs_label, freqs_ratio));
+        } else {
+            dstats_gs.push(App::gauge_not_available());
+        }
 
-        let pwr = dinfo.dev_stats.power.back().unwrap();  // always present
-        let pwr_label = Span::styled(
-            format!("{:.1}/{:.1}", pwr.gpu_cur_power, pwr.pkg_cur_power),
-            Style::new().white());
-        let pwr_ratio = if pwr.pkg_cur_power > 0.0 {
-            pwr.gpu_cur_power / pwr.pkg_cur_power } else { 0.0 };
-        dstats_gs.push(App::gauge_colored_from(pwr_label, pwr_ratio));
+        if let Some(pwr) = dinfo.dev_stats.power.back() {
+            let pwr_label = Span::styled(
+                format!("{:.1}/{:.1}", pwr.gpu_cur_power, pwr.pkg_cur_power),
+                Style::new().white());
+            let pwr_ratio = if pwr.pkg_cur_power > 0.0 {
+                pwr.gpu_cur_power / pwr.pkg_cur_power } else { 0.0 };
+            dstats_gs.push(App::gauge_colored_from(&model.theme,
+                pwr_label, pwr_ratio));
+        } else {
+            dstats_gs.push(App::gauge_not_available());
+        }
+
+        // show the sensor closest to its critical/max limit
+        let hot = temps.iter().max_by(|a, b| {
+            let ra = if a.limit() > 0.0 { a.temp / a.limit() } else { 0.0 };
+            let rb = if b.limit() > 0.0 { b.temp / b.limit() } else { 0.0 };
+            ra.total_cmp(&rb).then(a.temp.total_cmp(&b.temp))
+        });
+        if let Some(tp) = hot {
+            let temp_label = Span::styled(
+                format!("{:.0}°C", tp.temp), Style::new().white());
+            let temp_ratio = tp.temp / if tp.limit() > 0.0 {
+                tp.limit() } else { 100.0 };
+            dstats_gs.push(App::gauge_temp_from(&model.theme,
+                temp_label, temp_ratio));
+        }
+
+        // show the fastest spinning fan
+        if let Some(fan) = fans.iter().max_by_key(|f| f.speed) {
+            let fan_label = Span::styled(
+                format!("{}", fan.speed), Style::new().white());
+            let fan_ratio = if fan.duty > 0.0 {
+                fan.duty / 100.0
+            } else if fan.max > 0 {
+                fan.speed as f64 / fan.max as f64
+            } else {
+                0.0
+            };
+            dstats_gs.push(App::gauge_colored_from(&model.theme,
+                fan_label, fan_ratio));
+        }
 
         for (ds_g, ds_a) in dstats_gs.iter().zip(gs_areas.iter()) {
             frame.render_widget(ds_g, *ds_a);
@@ -892,7 +1379,6 @@ impl MainScreen
         let x_bounds: [f64; 2];
         let mut x_labels: Vec<Span>;
         if x_vals.len() == 1 {
-            let model = self.model.borrow();
             let int_secs = model.args.ms_interval as f64 / 1000.0;
             x_bounds = [x_vals[0], x_vals[0] + int_secs];
             x_labels = vec![
@@ -933,6 +1419,18 @@ impl MainScreen
                 self.render_engines_chart(
                     &x_vals, x_axis, dinfo, frame, chart_area);
             },
+            DEVICE_STATS_TEMPS => {
+                self.render_temps_chart(
+                    &x_vals, x_axis, dinfo, frame, chart_area);
+            },
+            DEVICE_STATS_FANS => {
+                self.render_fans_chart(
+                    &x_vals, x_axis, dinfo, frame, chart_area);
+            },
+            DEVICE_STATS_THROTTLE => {
+                self.render_throttle_chart(
+                    &x_vals, x_axis, dinfo, frame, chart_area);
+            },
             _ => {
                 error!("Unknown device stats selection: {:?}", ds_st.sel);
             }
@@ -999,10 +1497,20 @@ impl MainScreen
         self.render_drm_clients(dinfo, frame, clis_stats_area);
     }
 
-    fn render_devs_tab(&self,
+    fn render_devs_tab(&self, data: &AppData,
         devs_ts: &DevicesTabState, frame: &mut Frame, area: Rect)
     {
-        frame.render_widget(Tabs::new(devs_ts.devs.clone())
+        let mut titles = Vec::new();
+        for dn in devs_ts.devs.iter() {
+            let sriov = data.get_device(dn).and_then(|di| di.sriov.as_ref());
+            titles.push(match sriov {
+                Some(sr) if sr.is_vf() => format!("{} (VF{})", dn, sr.vf_id),
+                Some(sr) if !sr.vf_devs.is_empty() => format!("{} (PF)", dn),
+                _ => dn.clone(),
+            });
+        }
+
+        frame.render_widget(Tabs::new(titles)
             .style(Style::new().white().bold().on_black())
             .highlight_style(Style::new().magenta().bold().on_black())
             .select(devs_ts.sel),
@@ -1011,10 +1519,13 @@ impl MainScreen
 
     pub fn new(model: Rc<RefCell<AppModel>>) -> Box<dyn Screen>
     {
+        let dstats_st = DeviceStatsState::new(
+            model.borrow().config.chart.as_deref());
+
         Box::new(MainScreen {
             model,
             tab_state: None,
-            dstats_state: RefCell::new(DeviceStatsState::new()),
+            dstats_state: RefCell::new(dstats_st),
             clis_state: RefCell::new(ClientsViewState::new()),
         })
     }
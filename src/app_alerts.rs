@@ -0,0 +1,458 @@
+use std::collections::HashMap;
+use std::ffi::CString;
+use std::process::{Child, Command, Stdio};
+
+use anyhow::{bail, Context, Result};
+use log::debug;
+
+use crate::app_data::{
+    engine_class, AppData, AppDataDeviceState, AppDataClientStats,
+    AppDataEngineStats};
+use crate::drm_devices::DrmDeviceThrottleReasons;
+
+
+// parses a number with an optional K/M/G/T binary suffix, e.g. "4G"
+pub fn parse_size(val: &str) -> Result<f64>
+{
+    let val = val.trim();
+    let (num, mult) = match val.chars().last() {
+        Some('K') | Some('k') => (&val[..val.len() - 1], 1024.0),
+        Some('M') => (&val[..val.len() - 1], 1024.0 * 1024.0),
+        Some('G') => (&val[..val.len() - 1], 1024.0 * 1024.0 * 1024.0),
+        Some('T') => (&val[..val.len() - 1], 1024.0 * 1024.0 * 1024.0 * 1024.0),
+        _ => (val, 1.0),
+    };
+    let res: f64 = num.trim().parse()
+        .with_context(|| format!("Invalid number {:?}", val))?;
+
+    Ok(res * mult)
+}
+
+// parses a duration in ms, seconds (default), minutes or hours, e.g. "30s"
+fn parse_duration_ms(val: &str) -> Result<u128>
+{
+    let val = val.trim();
+    let (num, mult) = if let Some(n) = val.strip_suffix("ms") {
+        (n, 1.0)
+    } else if let Some(n) = val.strip_suffix('s') {
+        (n, 1000.0)
+    } else if let Some(n) = val.strip_suffix('m') {
+        (n, 60.0 * 1000.0)
+    } else if let Some(n) = val.strip_suffix('h') {
+        (n, 3600.0 * 1000.0)
+    } else {
+        (val, 1000.0)
+    };
+    let res: f64 = num.trim().parse()
+        .with_context(|| format!("Invalid duration {:?}", val))?;
+    if res < 0.0 {
+        bail!("Negative duration {:?}", val);
+    }
+
+    Ok((res * mult) as u128)
+}
+
+fn throttle_reason(tr: &DrmDeviceThrottleReasons, name: &str) -> Option<bool>
+{
+    tr.reasons().iter().find(|(rn, _)| *rn == name).map(|(_, v)| *v)
+}
+
+// usage of the busiest engine matching an engine class or name
+fn engine_usage(eng_stats: &HashMap<String, AppDataEngineStats>,
+    name: &str) -> Option<f64>
+{
+    eng_stats.iter()
+        .filter(|(en, _)| *en == name || engine_class(en) == name)
+        .filter_map(|(_, est)| est.usage.back().copied())
+        .reduce(f64::max)
+}
+
+#[derive(Debug)]
+enum AppAlertOperand
+{
+    Number(f64),
+    Device(String),
+    Client(String),
+}
+
+impl AppAlertOperand
+{
+    fn is_client(&self) -> bool
+    {
+        matches!(self, AppAlertOperand::Client(_))
+    }
+
+    fn device_value(ds: &AppDataDeviceState, field: &str) -> Option<f64>
+    {
+        let dst = &ds.dev_stats;
+
+        if let Some(en) = field.strip_prefix("engine.") {
+            return engine_usage(&dst.eng_stats, en);
+        }
+        if let Some(tr) = field.strip_prefix("throttle_reasons.") {
+            let freqs = dst.freqs.back()?;
+            return throttle_reason(&freqs.throttle_reasons, tr)
+                .map(|v| if v { 1.0 } else { 0.0 });
+        }
+
+        match field {
+            "smem_used" => dst.mem_info.back().map(|m| m.smem_used as f64),
+            "smem_total" => dst.mem_info.back().map(|m| m.smem_total as f64),
+            "vram_used" => dst.mem_info.back().map(|m| m.vram_used as f64),
+            "vram_total" => dst.mem_info.back().map(|m| m.vram_total as f64),
+            "min_freq" => dst.freqs.back().map(|f| f.min_freq as f64),
+            "cur_freq" => dst.freqs.back().map(|f| f.cur_freq as f64),
+            "act_freq" => dst.freqs.back().map(|f| f.act_freq as f64),
+            "max_freq" => dst.freqs.back().map(|f| f.max_freq as f64),
+            "gpu_power" => dst.power.back().map(|p| p.gpu_cur_power),
+            "pkg_power" => dst.power.back().map(|p| p.pkg_cur_power),
+            "temp" => dst.temps.back()
+                .and_then(|tl| tl.iter().map(|t| t.temp).reduce(f64::max)),
+            _ => None,
+        }
+    }
+
+    fn client_value(cli: &AppDataClientStats, field: &str) -> Option<f64>
+    {
+        if let Some(en) = field.strip_prefix("engine.") {
+            return engine_usage(&cli.eng_stats, en);
+        }
+
+        match field {
+            "cpu" => cli.cpu_usage.back().copied(),
+            "smem_used" => cli.mem_info.back().map(|m| m.smem_used as f64),
+            "smem_rss" => cli.mem_info.back().map(|m| m.smem_rss as f64),
+            "vram_used" => cli.mem_info.back().map(|m| m.vram_used as f64),
+            "vram_rss" => cli.mem_info.back().map(|m| m.vram_rss as f64),
+            "power" => cli.power.back().copied(),
+            _ => None,
+        }
+    }
+
+    fn value(&self, ds: &AppDataDeviceState,
+        cli: Option<&AppDataClientStats>) -> Option<f64>
+    {
+        match self {
+            AppAlertOperand::Number(n) => Some(*n),
+            AppAlertOperand::Device(f) => Self::device_value(ds, f),
+            AppAlertOperand::Client(f) => Self::client_value(cli?, f),
+        }
+    }
+
+    fn from(val: &str) -> Result<AppAlertOperand>
+    {
+        let val = val.trim();
+        if val.is_empty() {
+            bail!("Missing alert rule operand");
+        }
+        if val.starts_with(|c: char| c.is_ascii_digit() || c == '.') {
+            return Ok(AppAlertOperand::Number(parse_size(val)?));
+        }
+
+        if let Some(f) = val.strip_prefix("client.") {
+            let valid = f.starts_with("engine.") || matches!(f,
+                "cpu" | "smem_used" | "smem_rss" |
+                "vram_used" | "vram_rss" | "power");
+            if !valid {
+                bail!("Unknown DRM client field {:?}", val);
+            }
+            return Ok(AppAlertOperand::Client(f.to_string()));
+        }
+
+        let valid = val.starts_with("engine.") ||
+            val.strip_prefix("throttle_reasons.").is_some_and(|tr|
+                throttle_reason(&DrmDeviceThrottleReasons::new(), tr)
+                    .is_some()) ||
+            matches!(val,
+                "smem_used" | "smem_total" | "vram_used" | "vram_total" |
+                "min_freq" | "cur_freq" | "act_freq" | "max_freq" |
+                "gpu_power" | "pkg_power" | "temp");
+        if !valid {
+            bail!("Unknown device field {:?}", val);
+        }
+
+        Ok(AppAlertOperand::Device(val.to_string()))
+    }
+}
+
+// <field>[/<field>] [<op> <value>] [for <duration>], e.g.
+// "vram_used/vram_total > 0.9 for 30s", "throttle_reasons.thermal" or
+// "client.engine.render > 80"
+#[derive(Debug)]
+pub struct AppAlertRule
+{
+    pub text: String,
+    lhs: AppAlertOperand,
+    div: Option<AppAlertOperand>,
+    op: String,
+    rhs: f64,
+    for_ms: u128,
+}
+
+impl AppAlertRule
+{
+    fn is_per_client(&self) -> bool
+    {
+        self.lhs.is_client() || self.div.as_ref().is_some_and(|d| d.is_client())
+    }
+
+    fn value(&self, ds: &AppDataDeviceState,
+        cli: Option<&AppDataClientStats>) -> Option<f64>
+    {
+        let val = self.lhs.value(ds, cli)?;
+        if let Some(div) = &self.div {
+            let dval = div.value(ds, cli)?;
+            if dval == 0.0 {
+                return None;
+            }
+            return Some(val / dval);
+        }
+
+        Some(val)
+    }
+
+    fn matches(&self, val: f64) -> bool
+    {
+        match self.op.as_str() {
+            ">" => val > self.rhs,
+            ">=" => val >= self.rhs,
+            "<" => val < self.rhs,
+            "<=" => val <= self.rhs,
+            "==" => val == self.rhs,
+            _ => val != self.rhs,
+        }
+    }
+
+    pub fn from(rule: &str) -> Result<AppAlertRule>
+    {
+        let text = rule.trim().to_string();
+
+        let (cond, for_ms) = if let Some((c, d)) = text.split_once(" for ") {
+            (c, parse_duration_ms(d)?)
+        } else {
+            (text.as_str(), 0)
+        };
+
+        let mut op = String::from("!=");
+        let mut rhs: f64 = 0.0;
+        let mut expr = cond;
+        for o in [">=", "<=", "==", "!=", ">", "<"] {
+            if let Some((e, v)) = cond.split_once(o) {
+                op = o.to_string();
+                rhs = parse_size(v)?;
+                expr = e;
+                break;
+            }
+        }
+
+        let (lhs, div) = if let Some((l, d)) = expr.split_once('/') {
+            (AppAlertOperand::from(l)?, Some(AppAlertOperand::from(d)?))
+        } else {
+            (AppAlertOperand::from(expr)?, None)
+        };
+        if let AppAlertOperand::Number(_) = lhs {
+            bail!("Alert rule {:?} must start with a field", text);
+        }
+
+        Ok(AppAlertRule {
+            text,
+            lhs,
+            div,
+            op,
+            rhs,
+            for_ms,
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct AppAlert
+{
+    pub rule: String,
+    pub pci_dev: String,
+    pub client: Option<String>,
+    pub value: f64,
+    since: u128,
+    pub firing: bool,
+    seen: bool,
+}
+
+impl AppAlert
+{
+    pub fn target(&self) -> String
+    {
+        if let Some(cli) = &self.client {
+            format!("{} {}", self.pci_dev, cli)
+        } else {
+            self.pci_dev.clone()
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct AppAlerts
+{
+    rules: Vec<AppAlertRule>,
+    alerts: HashMap<String, AppAlert>,
+    hook: Option<String>,
+    syslog: bool,
+    hook_procs: Vec<Child>,
+}
+
+impl AppAlerts
+{
+    pub fn add_rule(&mut self, rule: &str) -> Result<()>
+    {
+        self.rules.push(AppAlertRule::from(rule)?);
+
+        Ok(())
+    }
+
+    pub fn set_hook(&mut self, cmd: &str)
+    {
+        self.hook = Some(cmd.to_string());
+    }
+
+    pub fn set_syslog(&mut self, enable: bool)
+    {
+        if enable && !self.syslog {
+            unsafe {
+                libc::openlog(c"qmassa".as_ptr(), libc::LOG_PID, libc::LOG_USER);
+            }
+        }
+        self.syslog = enable;
+    }
+
+    pub fn firing(&self) -> Vec<&AppAlert>
+    {
+        let mut res: Vec<&AppAlert> = self.alerts.values()
+            .filter(|a| a.firing).collect();
+        res.sort_by_key(|a| a.since);
+
+        res
+    }
+
+    fn notify(&mut self, alert: &AppAlert)
+    {
+        let state = if alert.firing { "fired" } else { "cleared" };
+        let msg = format!("alert {}: {} on {} (value {:.2})",
+            state, alert.rule, alert.target(), alert.value);
+        debug!("INF: {}", msg);
+
+        if self.syslog {
+            if let Ok(cmsg) = CString::new(msg.clone()) {
+                let prio = if alert.firing {
+                    libc::LOG_WARNING } else { libc::LOG_NOTICE };
+                unsafe {
+                    libc::syslog(prio, c"%s".as_ptr(), cmsg.as_ptr());
+                }
+            }
+        }
+
+        if let Some(hook) = &self.hook {
+            let res = Command::new("sh")
+                .arg("-c")
+                .arg(hook)
+                .env("QMASSA_ALERT", &alert.rule)
+                .env("QMASSA_ALERT_STATE", state)
+                .env("QMASSA_ALERT_DEVICE", &alert.pci_dev)
+                .env("QMASSA_ALERT_CLIENT",
+                    alert.client.as_deref().unwrap_or(""))
+                .env("QMASSA_ALERT_VALUE", format!("{}", alert.value))
+                .stdin(Stdio::null())
+                .stdout(Stdio::null())
+                .stderr(Stdio::null())
+                .spawn();
+            match res {
+                Ok(ch) => self.hook_procs.push(ch),
+                Err(err) => debug!("ERR: failed to run alert hook: {}", err),
+            }
+        }
+    }
+
+    fn check(&mut self, key: String, rule_idx: usize,
+        ds: &AppDataDeviceState, cli: Option<&AppDataClientStats>, now: u128)
+    {
+        let rule = &self.rules[rule_idx];
+        let val = rule.value(ds, cli);
+        let active = val.is_some_and(|v| rule.matches(v));
+
+        if !active {
+            // cleared alerts are handled with the ones not seen anymore
+            return;
+        }
+
+        let alert = self.alerts.entry(key).or_insert_with(|| AppAlert {
+            rule: rule.text.clone(),
+            pci_dev: ds.pci_dev.clone(),
+            client: cli.map(|c| format!("[{}] {}", c.pid, c.comm)),
+            value: 0.0,
+            since: now,
+            firing: false,
+            seen: false,
+        });
+        alert.value = val.unwrap();
+        alert.seen = true;
+
+        if !alert.firing && now - alert.since >= rule.for_ms {
+            alert.firing = true;
+            let fired = alert.clone();
+            self.notify(&fired);
+        }
+    }
+
+    // evaluates all rules on the latest stats, running the hook or logging
+    // to syslog whenever an alert fires or clears
+    pub fn update(&mut self, data: &AppData)
+    {
+        if self.rules.is_empty() {
+            return;
+        }
+        let now = *data.timestamps().back().unwrap_or(&0);
+
+        // reap finished hook commands
+        self.hook_procs.retain_mut(|ch| matches!(ch.try_wait(), Ok(None)));
+
+        for alert in self.alerts.values_mut() {
+            alert.seen = false;
+        }
+
+        for ds in data.devices().iter() {
+            for ri in 0..self.rules.len() {
+                if !self.rules[ri].is_per_client() {
+                    let key = format!("{}/{}", ri, ds.pci_dev);
+                    self.check(key, ri, ds, None, now);
+                    continue;
+                }
+
+                for cli in ds.clis_stats.iter() {
+                    let key = format!("{}/{}/{}/{}",
+                        ri, ds.pci_dev, cli.drm_minor, cli.client_id);
+                    self.check(key, ri, ds, Some(cli), now);
+                }
+            }
+        }
+
+        let gone: Vec<String> = self.alerts.iter()
+            .filter(|(_, a)| !a.seen)
+            .map(|(k, _)| k.clone()).collect();
+        for key in gone.iter() {
+            let mut alert = self.alerts.remove(key).unwrap();
+            if alert.firing {
+                alert.firing = false;
+                self.notify(&alert);
+            }
+        }
+    }
+
+    pub fn new() -> AppAlerts
+    {
+        AppAlerts {
+            rules: Vec::new(),
+            alerts: HashMap::new(),
+            hook: None,
+            syslog: false,
+            hook_procs: Vec::new(),
+        }
+    }
+}
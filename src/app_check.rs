@@ -0,0 +1,185 @@
+use std::fmt;
+
+use anyhow::{bail, Context, Result};
+
+use crate::app_alerts::parse_size;
+use crate::app_data::engine_class;
+use crate::app_summary::{AppSummary, AppSummaryDevice};
+use crate::CheckLimits;
+
+
+// exit code when the command succeeded but some limit was violated
+pub const CHECK_FAILED_EXIT_CODE: i32 = 3;
+
+const MIB: f64 = 1024.0 * 1024.0;
+
+#[derive(Debug)]
+pub struct AppCheckResult
+{
+    pub desc: String,
+    pub passed: bool,
+}
+
+impl fmt::Display for AppCheckResult
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result
+    {
+        writeln!(f, "  {} {}",
+            if self.passed { "PASS" } else { "FAIL" }, self.desc)
+    }
+}
+
+// limits checked against the summary of a command run, the max ones must
+// hold on all devices and the min ones on at least one of them
+#[derive(Debug)]
+pub struct AppCheck
+{
+    max_power: Option<f64>,
+    max_avg_power: Option<f64>,
+    max_energy: Option<f64>,
+    max_vram: Option<f64>,
+    max_smem: Option<f64>,
+    max_throttle: Option<f64>,
+    min_avg_engine: Vec<(String, f64)>,
+    max_avg_engine: Vec<(String, f64)>,
+}
+
+impl AppCheck
+{
+    // parses "<class>=<pct>,..." with engine classes or engine names
+    fn parse_engine_limits(limits: &Vec<String>) -> Result<Vec<(String, f64)>>
+    {
+        let mut res = Vec::new();
+
+        for el in limits.iter().flat_map(|l| l.split(',')) {
+            if let Some((eng, pct)) = el.split_once('=') {
+                let pval: f64 = pct.trim().parse()
+                    .with_context(|| format!("Invalid engine limit {:?}", el))?;
+                res.push((eng.trim().to_string(), pval));
+            } else {
+                bail!("Invalid engine limit {:?}, expected <class>=<pct>", el);
+            }
+        }
+
+        Ok(res)
+    }
+
+    // average usage of the busiest engine matching an engine class or name
+    fn engine_avg(sdev: &AppSummaryDevice, eng: &str) -> Option<f64>
+    {
+        sdev.eng_usage.iter()
+            .filter(|(en, _)| *en == eng || engine_class(en) == eng)
+            .map(|(_, st)| st.avg())
+            .reduce(f64::max)
+    }
+
+    fn check_max(res: &mut Vec<AppCheckResult>, summary: &AppSummary,
+        name: &str, unit: &str, scale: f64, limit: Option<f64>,
+        stat: impl Fn(&AppSummaryDevice) -> Option<f64>)
+    {
+        let limit = if let Some(l) = limit { l } else { return; };
+        let mut found = false;
+
+        for sdev in summary.devices.iter() {
+            if let Some(val) = stat(sdev) {
+                found = true;
+                res.push(AppCheckResult {
+                    desc: format!("{}: {} {:.1} {} (max {:.1} {})",
+                        sdev.pci_dev, name, val / scale, unit,
+                        limit / scale, unit),
+                    passed: val <= limit,
+                });
+            }
+        }
+
+        if !found {
+            res.push(AppCheckResult {
+                desc: format!("{}: not available on any device", name),
+                passed: false,
+            });
+        }
+    }
+
+    fn check_engines(res: &mut Vec<AppCheckResult>, summary: &AppSummary,
+        limits: &Vec<(String, f64)>, is_min: bool)
+    {
+        for (eng, limit) in limits.iter() {
+            let vals: Vec<(&String, f64)> = summary.devices.iter()
+                .filter_map(|sd| Self::engine_avg(sd, eng)
+                    .map(|v| (&sd.pci_dev, v)))
+                .collect();
+            let kind = if is_min { "min" } else { "max" };
+
+            if vals.is_empty() {
+                res.push(AppCheckResult {
+                    desc: format!("avg {} usage: not available on any device",
+                        eng),
+                    passed: false,
+                });
+                continue;
+            }
+
+            if is_min {
+                // the busiest device is the one the command used
+                let (dev, val) = vals.iter()
+                    .max_by(|a, b| a.1.total_cmp(&b.1)).unwrap();
+                res.push(AppCheckResult {
+                    desc: format!("{}: avg {} usage {:.1}% ({} {:.1}%)",
+                        dev, eng, val, kind, limit),
+                    passed: val >= limit,
+                });
+            } else {
+                for (dev, val) in vals.iter() {
+                    res.push(AppCheckResult {
+                        desc: format!("{}: avg {} usage {:.1}% ({} {:.1}%)",
+                            dev, eng, val, kind, limit),
+                        passed: val <= limit,
+                    });
+                }
+            }
+        }
+    }
+
+    pub fn results(&self, summary: &AppSummary) -> Vec<AppCheckResult>
+    {
+        let mut res = Vec::new();
+
+        Self::check_max(&mut res, summary, "peak GPU power", "W", 1.0,
+            self.max_power, |sd| if sd.gpu_power.is_empty() {
+                None } else { Some(sd.gpu_power.max()) });
+        Self::check_max(&mut res, summary, "avg GPU power", "W", 1.0,
+            self.max_avg_power, |sd| if sd.gpu_power.is_empty() {
+                None } else { Some(sd.gpu_power.avg()) });
+        Self::check_max(&mut res, summary, "GPU energy", "J", 1.0,
+            self.max_energy, |sd| sd.gpu_energy);
+        Self::check_max(&mut res, summary, "peak clients VRAM", "MiB", MIB,
+            self.max_vram, |sd| if sd.clis_vram_used.is_empty() {
+                None } else { Some(sd.clis_vram_used.max()) });
+        Self::check_max(&mut res, summary, "peak clients SMEM", "MiB", MIB,
+            self.max_smem, |sd| if sd.clis_smem_used.is_empty() {
+                None } else { Some(sd.clis_smem_used.max()) });
+        Self::check_max(&mut res, summary, "time throttled", "%", 1.0,
+            self.max_throttle, |sd| if sd.act_freq.is_empty() ||
+                sd.total_ms == 0 { None } else {
+                    Some(sd.throttle_ms as f64 * 100.0 / sd.total_ms as f64) });
+
+        Self::check_engines(&mut res, summary, &self.min_avg_engine, true);
+        Self::check_engines(&mut res, summary, &self.max_avg_engine, false);
+
+        res
+    }
+
+    pub fn from(limits: &CheckLimits) -> Result<AppCheck>
+    {
+        Ok(AppCheck {
+            max_power: limits.max_power,
+            max_avg_power: limits.max_avg_power,
+            max_energy: limits.max_energy,
+            max_vram: limits.max_vram.as_deref().map(parse_size).transpose()?,
+            max_smem: limits.max_smem.as_deref().map(parse_size).transpose()?,
+            max_throttle: limits.max_throttle,
+            min_avg_engine: Self::parse_engine_limits(&limits.min_avg_engine)?,
+            max_avg_engine: Self::parse_engine_limits(&limits.max_avg_engine)?,
+        })
+    }
+}
@@ -0,0 +1,194 @@
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use anyhow::{bail, Context, Result};
+use log::debug;
+use ratatui::style::{palette::tailwind, Color};
+use serde::Deserialize;
+
+
+pub const CONFIG_CHARTS: &[&str] = &[
+    "freqs", "power", "meminfo", "engines", "temps", "fans", "throttle"];
+pub const CONFIG_COLUMNS: &[&str] = &[
+    "smem", "vram", "min", "engines", "cpu", "power"];
+pub const CONFIG_SORTS: &[&str] = &[
+    "pid", "smem", "vram", "engines", "cpu", "power", "command"];
+
+// ratio thresholds for the gauges to turn orange and red
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AppConfigGauges
+{
+    pub orange: f64,
+    pub red: f64,
+}
+
+impl Default for AppConfigGauges
+{
+    fn default() -> AppConfigGauges
+    {
+        AppConfigGauges {
+            orange: 0.3,
+            red: 0.7,
+        }
+    }
+}
+
+// colors by name (e.g. "cyan", "lightblue"), index (e.g. "33") or
+// hex RGB (e.g. "#ff8800")
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AppConfigTheme
+{
+    pub border: Option<String>,
+    pub title: Option<String>,
+    pub selected: Option<String>,
+    pub gauge_green: Option<String>,
+    pub gauge_orange: Option<String>,
+    pub gauge_red: Option<String>,
+}
+
+#[derive(Debug)]
+pub struct AppTheme
+{
+    pub border: Color,
+    pub title: Color,
+    pub selected: Color,
+    pub gauge_green: Color,
+    pub gauge_orange: Color,
+    pub gauge_red: Color,
+    pub gauge_orange_ratio: f64,
+    pub gauge_red_ratio: f64,
+}
+
+impl AppTheme
+{
+    fn color_from(val: &Option<String>, default: Color) -> Result<Color>
+    {
+        if let Some(cstr) = val {
+            return Color::from_str(cstr)
+                .map_err(|_| anyhow::anyhow!("Invalid color {:?}", cstr));
+        }
+
+        Ok(default)
+    }
+
+    pub fn from(cfg: &AppConfig) -> Result<AppTheme>
+    {
+        let th = &cfg.theme;
+
+        Ok(AppTheme {
+            border: Self::color_from(&th.border, Color::Cyan)?,
+            title: Self::color_from(&th.title, Color::LightBlue)?,
+            selected: Self::color_from(&th.selected, Color::LightBlue)?,
+            gauge_green: Self::color_from(
+                &th.gauge_green, tailwind::GREEN.c500)?,
+            gauge_orange: Self::color_from(
+                &th.gauge_orange, tailwind::ORANGE.c500)?,
+            gauge_red: Self::color_from(&th.gauge_red, tailwind::RED.c500)?,
+            gauge_orange_ratio: cfg.gauges.orange,
+            gauge_red_ratio: cfg.gauges.red,
+        })
+    }
+}
+
+// settings from the config file, the ones also in the command-line
+// options are only used when those options aren't given
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AppConfig
+{
+    pub ms_interval: Option<u64>,
+    pub dev_slot: Option<String>,
+    pub all_clients: Option<bool>,
+    pub history: Option<usize>,
+    pub columns: Option<Vec<String>>,
+    pub chart: Option<String>,
+    pub sort: Option<String>,
+    pub gauges: AppConfigGauges,
+    pub theme: AppConfigTheme,
+}
+
+impl AppConfig
+{
+    // $XDG_CONFIG_HOME/qmassa/config.toml or ~/.config/qmassa/config.toml
+    fn default_path() -> Option<PathBuf>
+    {
+        let base = if let Some(xdg) = env::var_os("XDG_CONFIG_HOME") {
+            PathBuf::from(xdg)
+        } else if let Some(home) = env::var_os("HOME") {
+            PathBuf::from(home).join(".config")
+        } else {
+            return None;
+        };
+
+        Some(base.join("qmassa").join("config.toml"))
+    }
+
+    pub fn is_column_visible(&self, col: &str) -> bool
+    {
+        if let Some(cols) = &self.columns {
+            return cols.iter().any(|c| c == col);
+        }
+
+        true
+    }
+
+    fn validate(&self) -> Result<()>
+    {
+        if let Some(cols) = &self.columns {
+            for col in cols.iter() {
+                if !CONFIG_COLUMNS.contains(&col.as_str()) {
+                    bail!("Unknown column {:?}, expected one of {:?}",
+                        col, CONFIG_COLUMNS);
+                }
+            }
+        }
+        if let Some(chart) = &self.chart {
+            if !CONFIG_CHARTS.contains(&chart.as_str()) {
+                bail!("Unknown chart {:?}, expected one of {:?}",
+                    chart, CONFIG_CHARTS);
+            }
+        }
+        if let Some(sort) = &self.sort {
+            if !CONFIG_SORTS.contains(&sort.as_str()) {
+                bail!("Unknown sort order {:?}, expected one of {:?}",
+                    sort, CONFIG_SORTS);
+            }
+        }
+
+        let gs = &self.gauges;
+        if gs.orange < 0.0 || gs.orange > gs.red || gs.red > 1.0 {
+            bail!("Gauge thresholds must be 0 <= orange <= red <= 1");
+        }
+
+        Ok(())
+    }
+
+    // a missing config file is only an error if its path was given
+    pub fn load(fname: Option<&str>) -> Result<AppConfig>
+    {
+        let path = if let Some(fname) = fname {
+            PathBuf::from(fname)
+        } else if let Some(p) = Self::default_path() {
+            if !p.exists() {
+                return Ok(AppConfig::default());
+            }
+            p
+        } else {
+            return Ok(AppConfig::default());
+        };
+
+        debug!("INF: loading config file {:?}", path);
+        let cfg_str = fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read {:?}", path))?;
+        let cfg: AppConfig = toml::from_str(&cfg_str)
+            .with_context(|| format!("Failed to parse {:?}", path))?;
+        cfg.validate()
+            .with_context(|| format!("Invalid config in {:?}", path))?;
+
+        Ok(cfg)
+    }
+}
@@ -3,37 +3,251 @@ use std::cell::{RefCell, Ref};
 use std::rc::Rc;
 use std::time;
 
-use anyhow::Result;
+use anyhow::{bail, Result};
 use serde::{Deserialize, Serialize};
 
 use crate::drm_devices::{
-    DrmDeviceFreqLimits, DrmDeviceFreqs, DrmDevicePower,
-    DrmDeviceMemInfo, DrmDeviceInfo, DrmDevices};
+    DrmDeviceCapabilities, DrmDeviceFreqLimits, DrmDeviceDevfreq,
+    DrmDeviceBlockStats,
+    DrmDeviceFreqs, DrmDevicePower, DrmDevicePowerLimit, DrmDeviceMemInfo,
+    DrmDeviceTemperature, DrmDeviceFan, DrmDeviceFirmware, DrmDevicePciInfo,
+    DrmDeviceSriov, DrmDeviceInfo, DrmDevices};
 use crate::drm_clients::{DrmClientMemInfo, DrmClientInfo};
 
 
-const APP_DATA_MAX_NR_STATS: usize = 40;
+const APP_DATA_DEFAULT_NR_STATS: usize = 40;
+const APP_DATA_MAX_NR_STATS: usize = 3600;
+const APP_DATA_DEFAULT_LEAK_WINDOW: usize = 20;
+// fraction of the leak window updates the memory must grow on, and its
+// minimum growth rate (MiB/min)
+const APP_DATA_LEAK_MIN_INCS: f64 = 0.75;
+const APP_DATA_LEAK_MIN_RATE: f64 = 1.0;
 
-fn limited_vec_push<T>(vlst: &mut VecDeque<T>, vitem: T)
+fn is_zero(val: &f64) -> bool
 {
-    if vlst.len() == APP_DATA_MAX_NR_STATS {
+    *val == 0.0
+}
+
+// engines busy time (in engine-seconds per second) in the last iteration,
+// usage being normalized by the engines capacity, weighted by the engine
+// class power weights
+fn weighted_busy(eng_stats: &HashMap<String, AppDataEngineStats>,
+    power_weights: &HashMap<String, f64>) -> f64
+{
+    let mut res: f64 = 0.0;
+
+    for (en, est) in eng_stats.iter() {
+        let eut = est.usage.back().unwrap_or(&0.0);
+        let w = power_weights.get(engine_class(en))
+            .or(power_weights.get(en)).unwrap_or(&1.0);
+        res += w * eut / 100.0 * est.capacity as f64;
+    }
+
+    res
+}
+
+fn limited_vec_push<T>(vlst: &mut VecDeque<T>, vitem: T, nr_stats: usize)
+{
+    while vlst.len() >= nr_stats {
         vlst.pop_front();
     }
     vlst.push_back(vitem);
 }
 
+// engine classes (for the grouped engines view and the clients power
+// attribution weights) and the engine names drivers report for them in DRM
+// fdinfo (instance suffixes like "_1" are ignored), engines not listed here
+// are a class of their own
+pub const ENGINE_CLASSES: &[(&str, &[&str])] = &[
+    ("render", &["rcs", "render", "gfx", "gpu", "3d", "fragment",
+        "vertex-tiler", "panthor", "bin", "gp", "pp"]),
+    ("copy", &["bcs", "copy", "dma", "sdma", "blit", "tfu"]),
+    ("video", &["vcs", "video", "dec", "enc", "vcn", "jpeg", "uvd", "vce"]),
+    ("video-enhance", &["vecs", "video-enhance", "vpe"]),
+    ("compute", &["ccs", "compute", "npu", "csd"]),
+];
+
+pub fn engine_class(eng: &str) -> &str
+{
+    let base = eng.trim_end_matches(|c: char| c.is_ascii_digit())
+        .trim_end_matches('_');
+
+    for (cls, names) in ENGINE_CLASSES.iter() {
+        if names.contains(&base) {
+            return cls;
+        }
+    }
+
+    eng
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct AppDataEngineStats
 {
+    pub capacity: u32,
     pub usage: VecDeque<f64>,
+    // engine frequencies (in MHz) aligned with usage, None on the samples
+    // the DRM client didn't report them
+    #[serde(default, skip_serializing_if = "no_freqs")]
+    pub cur_freq: VecDeque<Option<u64>>,
+    #[serde(default, skip_serializing_if = "no_freqs")]
+    pub max_freq: VecDeque<Option<u64>>,
+}
+
+fn no_freqs(vlst: &VecDeque<Option<u64>>) -> bool
+{
+    vlst.iter().all(|v| v.is_none())
 }
 
 impl AppDataEngineStats
 {
+    pub fn has_freqs(&self) -> bool
+    {
+        !no_freqs(&self.cur_freq)
+    }
+
+    // (cur, max) frequencies at the given sample, if reported
+    pub fn freqs(&self, idx: usize) -> Option<(u64, u64)>
+    {
+        let cur = (*self.cur_freq.get(idx)?)?;
+        let mx = (*self.max_freq.get(idx)?)?;
+
+        Some((cur, mx))
+    }
+
+    pub fn last_freqs(&self) -> Option<(u64, u64)>
+    {
+        self.freqs(self.usage.len().checked_sub(1)?)
+    }
+
     fn new() -> AppDataEngineStats
     {
         AppDataEngineStats {
+            capacity: 1,
             usage: VecDeque::new(),
+            cur_freq: VecDeque::new(),
+            max_freq: VecDeque::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AppDataDeviceEnergy
+{
+    // all energy values in joules, mark times in ms since qmassa started
+    pub gpu_energy: f64,
+    pub pkg_energy: f64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mark_start: Option<u128>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mark_end: Option<u128>,
+    pub mark_gpu_energy: f64,
+    pub mark_pkg_energy: f64,
+}
+
+impl AppDataDeviceEnergy
+{
+    pub fn is_marking(&self) -> bool
+    {
+        self.mark_start.is_some() && self.mark_end.is_none()
+    }
+
+    // starts a new marked interval or ends the current one
+    fn toggle_mark(&mut self, now: u128)
+    {
+        if self.is_marking() {
+            self.mark_end = Some(now);
+        } else {
+            self.mark_start = Some(now);
+            self.mark_end = None;
+            self.mark_gpu_energy = 0.0;
+            self.mark_pkg_energy = 0.0;
+        }
+    }
+
+    fn update(&mut self, pwr: &DrmDevicePower, ms_elapsed: u64)
+    {
+        // use the energy counters deltas when the driver has them,
+        // otherwise integrate the instant power over the interval
+        let secs = ms_elapsed as f64 / 1000.0;
+        let gpu_nrg = if pwr.gpu_energy > 0.0 {
+            pwr.gpu_energy } else { pwr.gpu_cur_power * secs };
+        let pkg_nrg = if pwr.pkg_energy > 0.0 {
+            pwr.pkg_energy } else { pwr.pkg_cur_power * secs };
+
+        self.gpu_energy += gpu_nrg;
+        self.pkg_energy += pkg_nrg;
+        if self.is_marking() {
+            self.mark_gpu_energy += gpu_nrg;
+            self.mark_pkg_energy += pkg_nrg;
+        }
+    }
+
+    fn new() -> AppDataDeviceEnergy
+    {
+        AppDataDeviceEnergy {
+            gpu_energy: 0.0,
+            pkg_energy: 0.0,
+            mark_start: None,
+            mark_end: None,
+            mark_gpu_energy: 0.0,
+            mark_pkg_energy: 0.0,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AppDataThrottleReason
+{
+    pub name: String,
+    pub active_ms: u64,
+    pub residency: f64,     // % of the session time
+}
+
+// time each throttle reason was active over the whole session, not only
+// over the latest stats
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AppDataThrottleResidency
+{
+    pub total_ms: u64,
+    pub reasons: Vec<AppDataThrottleReason>,
+}
+
+impl AppDataThrottleResidency
+{
+    fn update(&mut self, freqs: &DrmDeviceFreqs, ms_elapsed: u64)
+    {
+        self.total_ms += ms_elapsed;
+
+        for (name, active) in freqs.throttle_reasons.reasons().iter() {
+            let pos = self.reasons.iter().position(|r| r.name == *name);
+            let rsn = if let Some(idx) = pos {
+                &mut self.reasons[idx]
+            } else {
+                self.reasons.push(AppDataThrottleReason {
+                    name: name.to_string(),
+                    active_ms: 0,
+                    residency: 0.0,
+                });
+                self.reasons.last_mut().unwrap()
+            };
+
+            if *active {
+                rsn.active_ms += ms_elapsed;
+            }
+            rsn.residency = if self.total_ms > 0 {
+                rsn.active_ms as f64 * 100.0 / self.total_ms as f64
+            } else {
+                0.0
+            };
+        }
+    }
+
+    fn new() -> AppDataThrottleResidency
+    {
+        AppDataThrottleResidency {
+            total_ms: 0,
+            reasons: Vec::new(),
         }
     }
 }
@@ -41,27 +255,59 @@ impl AppDataEngineStats
 #[derive(Debug, Serialize, Deserialize)]
 pub struct AppDataDeviceStats
 {
+    // stats not supported by the driver are left empty and not exported
+    #[serde(default, skip_serializing_if = "VecDeque::is_empty")]
     pub freqs: VecDeque<DrmDeviceFreqs>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub throttle: Option<AppDataThrottleResidency>,
+    #[serde(default, skip_serializing_if = "VecDeque::is_empty")]
     pub power: VecDeque<DrmDevicePower>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub energy: Option<AppDataDeviceEnergy>,
     pub mem_info: VecDeque<DrmDeviceMemInfo>,
+    #[serde(default, skip_serializing_if = "VecDeque::is_empty")]
+    pub temps: VecDeque<Vec<DrmDeviceTemperature>>,
+    #[serde(default, skip_serializing_if = "VecDeque::is_empty")]
+    pub fans: VecDeque<Vec<DrmDeviceFan>>,
     pub eng_stats: HashMap<String, AppDataEngineStats>,
 }
 
 impl AppDataDeviceStats
 {
-    fn update_stats(&mut self,
-        eng_names: &Vec<String>, dinfo: &DrmDeviceInfo)
+    fn update_stats(&mut self, eng_names: &Vec<String>,
+        dinfo: &DrmDeviceInfo, ms_elapsed: u64, nr_stats: usize)
     {
-        limited_vec_push(&mut self.freqs, dinfo.freqs.clone());
-        limited_vec_push(&mut self.power, dinfo.power.clone());
-        limited_vec_push(&mut self.mem_info, dinfo.mem_info.clone());
+        if dinfo.caps.freqs {
+            limited_vec_push(&mut self.freqs, dinfo.freqs.clone(),
+                nr_stats);
+            self.throttle.get_or_insert_with(AppDataThrottleResidency::new)
+                .update(&dinfo.freqs, ms_elapsed);
+        }
+        if dinfo.caps.power {
+            limited_vec_push(&mut self.power, dinfo.power.clone(),
+                nr_stats);
+            self.energy.get_or_insert_with(AppDataDeviceEnergy::new)
+                .update(&dinfo.power, ms_elapsed);
+        }
+        limited_vec_push(&mut self.mem_info, dinfo.mem_info.clone(),
+            nr_stats);
+        if dinfo.caps.temps {
+            limited_vec_push(&mut self.temps, dinfo.temps.clone(),
+                nr_stats);
+        }
+        if dinfo.caps.fans {
+            limited_vec_push(&mut self.fans, dinfo.fans.clone(),
+                nr_stats);
+        }
 
         for en in eng_names.iter() {
             if !self.eng_stats.contains_key(en) {
                 self.eng_stats.insert(en.clone(), AppDataEngineStats::new());
             }
             let est = self.eng_stats.get_mut(en).unwrap();
-            limited_vec_push(&mut est.usage, dinfo.eng_utilization(en));
+            est.capacity = dinfo.eng_capacity(en);
+            limited_vec_push(&mut est.usage, dinfo.eng_utilization(en),
+                nr_stats);
         }
     }
 
@@ -75,13 +321,70 @@ impl AppDataDeviceStats
 
         AppDataDeviceStats {
             freqs: VecDeque::new(),
+            throttle: None,
             power: VecDeque::new(),
+            energy: None,
             mem_info: VecDeque::new(),
+            temps: VecDeque::new(),
+            fans: VecDeque::new(),
             eng_stats: estats,
         }
     }
 }
 
+// memory growth rates (MiB/min) over the leak detection window, and if
+// both the total and resident memory kept growing in it: never shrinking,
+// the total growing on most updates and at least at a minimum rate
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AppDataClientMemGrowth
+{
+    pub smem_rate: f64,
+    pub vram_rate: f64,
+    pub smem_leak: bool,
+    pub vram_leak: bool,
+}
+
+impl AppDataClientMemGrowth
+{
+    pub fn is_leaking(&self) -> bool
+    {
+        self.smem_leak || self.vram_leak
+    }
+
+    fn is_growing(used: &Vec<u64>, rss: &Vec<u64>, rate: f64) -> bool
+    {
+        let nr_steps = used.len() - 1;
+        let nr_incs = used.windows(2).filter(|w| w[1] > w[0]).count();
+
+        used.windows(2).all(|w| w[1] >= w[0]) &&
+            rss.windows(2).all(|w| w[1] >= w[0]) &&
+            rss.last() > rss.first() &&
+            nr_incs as f64 >= nr_steps as f64 * APP_DATA_LEAK_MIN_INCS &&
+            rate >= APP_DATA_LEAK_MIN_RATE
+    }
+
+    fn from(mis: &Vec<&DrmClientMemInfo>, mins: f64) -> AppDataClientMemGrowth
+    {
+        let smem: Vec<u64> = mis.iter().map(|mi| mi.smem_used).collect();
+        let smem_rss: Vec<u64> = mis.iter().map(|mi| mi.smem_rss).collect();
+        let vram: Vec<u64> = mis.iter().map(|mi| mi.vram_used).collect();
+        let vram_rss: Vec<u64> = mis.iter().map(|mi| mi.vram_rss).collect();
+        let rate = |vals: &Vec<u64>| if mins > 0.0 {
+            (vals[vals.len() - 1] as f64 - vals[0] as f64) /
+                (1024.0 * 1024.0) / mins } else { 0.0 };
+
+        let smem_rate = rate(&smem);
+        let vram_rate = rate(&vram);
+
+        AppDataClientMemGrowth {
+            smem_rate,
+            vram_rate,
+            smem_leak: Self::is_growing(&smem, &smem_rss, smem_rate),
+            vram_leak: Self::is_growing(&vram, &vram_rss, vram_rate),
+        }
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct AppDataClientStats
 {
@@ -93,28 +396,67 @@ pub struct AppDataClientStats
     pub cpu_usage: VecDeque<f64>,
     pub eng_stats: HashMap<String, AppDataEngineStats>,
     pub mem_info: VecDeque<DrmClientMemInfo>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mem_growth: Option<AppDataClientMemGrowth>,
+    // estimated from the device GPU power, if the driver reports it
+    #[serde(default, skip_serializing_if = "VecDeque::is_empty")]
+    pub power: VecDeque<f64>,
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub energy: f64,
     pub is_active: bool,
 }
 
 impl AppDataClientStats
 {
-    fn update_stats(&mut self,
-        eng_names: &Vec<String>, cinfo: &DrmClientInfo)
+    fn update_stats(&mut self, eng_names: &Vec<String>,
+        cinfo: &DrmClientInfo, nr_stats: usize)
     {
-        limited_vec_push(&mut self.cpu_usage, cinfo.proc.cpu_utilization());
+        limited_vec_push(&mut self.cpu_usage, cinfo.proc.cpu_utilization(),
+            nr_stats);
 
         for en in eng_names.iter() {
             if !self.eng_stats.contains_key(en) {
                 self.eng_stats.insert(en.clone(), AppDataEngineStats::new());
             }
             let est = self.eng_stats.get_mut(en).unwrap();
-            limited_vec_push(&mut est.usage, cinfo.eng_utilization(en));
+            est.capacity = cinfo.eng_capacity(en);
+            limited_vec_push(&mut est.usage, cinfo.eng_utilization(en),
+                nr_stats);
+            let fqs = cinfo.eng_freqs(en);
+            limited_vec_push(&mut est.cur_freq, fqs.map(|(cur, _)| cur),
+                nr_stats);
+            limited_vec_push(&mut est.max_freq, fqs.map(|(_, mx)| mx),
+                nr_stats);
         }
-        limited_vec_push(&mut self.mem_info, cinfo.mem_info());
+        limited_vec_push(&mut self.mem_info, cinfo.mem_info(), nr_stats);
 
         self.is_active = cinfo.is_active();
     }
 
+    // mem_info is aligned with the end of the timestamps, as clients may
+    // have shown up later than the device
+    fn update_mem_growth(&mut self, tstamps: &VecDeque<u128>, window: usize)
+    {
+        let nr = self.mem_info.len();
+        if window < 2 || nr < window || tstamps.len() < window {
+            self.mem_growth = None;
+            return;
+        }
+
+        let mis: Vec<&DrmClientMemInfo> =
+            self.mem_info.range(nr - window..).collect();
+        let ms = tstamps[tstamps.len() - 1] - tstamps[tstamps.len() - window];
+
+        self.mem_growth = Some(AppDataClientMemGrowth::from(
+            &mis, ms as f64 / 60000.0));
+    }
+
+    fn update_power(&mut self, power: f64, ms_elapsed: u64, nr_stats: usize)
+    {
+        limited_vec_push(&mut self.power, power, nr_stats);
+        self.energy += power * ms_elapsed as f64 / 1000.0;
+    }
+
     fn from(eng_names: &Vec<String>,
         cinfo: &DrmClientInfo) -> AppDataClientStats
     {
@@ -133,6 +475,9 @@ impl AppDataClientStats
             cpu_usage: VecDeque::new(),
             eng_stats: estats,
             mem_info: VecDeque::new(),
+            mem_growth: None,
+            power: VecDeque::new(),
+            energy: 0.0,
             is_active: false,
         }
     }
@@ -143,11 +488,26 @@ pub struct AppDataDeviceState
 {
     pub pci_dev: String,
     pub vdr_dev_rev: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub subsys: String,
     pub dev_type: String,
     pub drv_name: String,
+    pub drv_version: String,
     pub dev_nodes: String,
     pub eng_names: Vec<String>,
+    pub caps: DrmDeviceCapabilities,
     pub freq_limits: DrmDeviceFreqLimits,
+    pub power_limits: Vec<DrmDevicePowerLimit>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub devfreq: Option<DrmDeviceDevfreq>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub block_stats: Option<DrmDeviceBlockStats>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub firmwares: Vec<DrmDeviceFirmware>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pci_info: Option<DrmDevicePciInfo>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sriov: Option<DrmDeviceSriov>,
     pub dev_stats: AppDataDeviceStats,
     pub clis_stats: Vec<AppDataClientStats>,
 }
@@ -193,12 +553,43 @@ impl AppDataDeviceState
         self.eng_names = neng_names;
     }
 
+    // splits the device GPU power among its DRM clients, proportionally to
+    // their share of the device-wide (weighted) engines busy time, so the
+    // share of busy time from clients not tracked is left unattributed
+    fn update_clients_power(&mut self, power_weights: &HashMap<String, f64>,
+        ms_elapsed: u64, nr_stats: usize)
+    {
+        if !self.caps.power {
+            return;
+        }
+        let gpu_power = if let Some(pwr) = self.dev_stats.power.back() {
+            pwr.gpu_cur_power } else { 0.0 };
+
+        let busy: Vec<f64> = self.clis_stats.iter()
+            .map(|cli| weighted_busy(&cli.eng_stats, power_weights))
+            .collect();
+        let dev_busy = weighted_busy(&self.dev_stats.eng_stats, power_weights);
+        let total = dev_busy.max(busy.iter().sum());
+
+        for (cli, cb) in self.clis_stats.iter_mut().zip(busy.iter()) {
+            let pwr = if total > 0.0 { gpu_power * cb / total } else { 0.0 };
+            cli.update_power(pwr, ms_elapsed, nr_stats);
+        }
+    }
+
     fn update_stats(&mut self, dinfo: &DrmDeviceInfo,
-        cinfos_b: &Option<Ref<'_, Vec<DrmClientInfo>>>)
+        cinfos_b: &Option<Ref<'_, Vec<DrmClientInfo>>>,
+        power_weights: &HashMap<String, f64>, ms_elapsed: u64,
+        nr_stats: usize)
     {
         self.update_eng_names(dinfo);
 
-        self.dev_stats.update_stats(&self.eng_names, dinfo);
+        self.dev_stats.update_stats(&self.eng_names, dinfo, ms_elapsed,
+            nr_stats);
+        self.devfreq = dinfo.devfreq.clone();
+        self.block_stats = dinfo.block_stats.clone();
+        self.pci_info = dinfo.pci_info.clone();
+        self.sriov = dinfo.sriov.clone();
 
         let mut ncstats: Vec<AppDataClientStats> = Vec::new();
         if let Some(clis_b) = cinfos_b {
@@ -212,18 +603,21 @@ impl AppDataDeviceState
                         &self.eng_names, cinf);
                 }
 
-                ncli_st.update_stats(&self.eng_names, cinf);
+                ncli_st.update_stats(&self.eng_names, cinf, nr_stats);
                 ncstats.push(ncli_st);
             }
         }
 
         self.clis_stats = ncstats;
+        self.update_clients_power(power_weights, ms_elapsed, nr_stats);
     }
 
     fn card_from(devnode: &String) -> &str
     {
         if devnode.starts_with("/dev/dri/") {
             &devnode["/dev/dri/".len()..]
+        } else if devnode.starts_with("/dev/accel/") {
+            &devnode["/dev/accel/".len()..]
         } else {
             devnode
         }
@@ -247,13 +641,32 @@ impl AppDataDeviceState
 
         AppDataDeviceState {
             pci_dev: dinfo.pci_dev.clone(),
-            vdr_dev_rev: format!("{} {} (rev {})",
-                dinfo.vendor, dinfo.device, dinfo.revision),
+            vdr_dev_rev: if dinfo.revision.is_empty() {
+                format!("{} {}", dinfo.vendor, dinfo.device).trim().to_string()
+            } else {
+                format!("{} {} (rev {})",
+                    dinfo.vendor, dinfo.device, dinfo.revision)
+            },
+            subsys: if dinfo.subsys_vendor_id.is_empty() {
+                String::new()
+            } else if dinfo.subsys_device.is_empty() {
+                format!("{} {}", dinfo.subsys_vendor, dinfo.subsys_device_id)
+            } else {
+                format!("{} {}", dinfo.subsys_vendor, dinfo.subsys_device)
+            },
             dev_type: dinfo.dev_type.to_string(),
             drv_name: dinfo.drv_name.clone(),
+            drv_version: dinfo.drv_version.clone(),
             dev_nodes: dnodes,
             eng_names: enames,
+            caps: dinfo.caps.clone(),
             freq_limits: dinfo.freq_limits.clone(),
+            power_limits: dinfo.power_limits.clone(),
+            devfreq: None,
+            block_stats: None,
+            firmwares: dinfo.firmwares.clone(),
+            pci_info: None,
+            sriov: None,
             dev_stats: dstats,
             clis_stats: Vec::new(),
         }
@@ -300,6 +713,9 @@ pub struct AppData
 {
     state: AppDataState,
     qmds: DrmDevices,
+    power_weights: HashMap<String, f64>,
+    leak_window: usize,
+    nr_stats: usize,
     start_time: time::Instant,
 }
 
@@ -335,6 +751,10 @@ impl AppData
     {
         self.qmds.refresh()?;
 
+        let now = self.start_time.elapsed().as_millis();
+        let ms_elapsed = if let Some(last) = self.state.timestamps.back() {
+            (now - last) as u64 } else { 0 };
+
         let mut nstate = AppDataState::new();
         for d in self.qmds.devices() {
             let dinfo = self.qmds.device_info(d).unwrap();
@@ -355,25 +775,83 @@ impl AppData
                 ndst = AppDataDeviceState::from(dinfo);
             }
 
-            ndst.update_stats(dinfo, &cinfos_b);
+            ndst.update_stats(dinfo, &cinfos_b,
+                &self.power_weights, ms_elapsed, self.nr_stats);
             nstate.devs_state.push(ndst);
         }
 
         nstate.timestamps.append(&mut self.state.timestamps);
-        limited_vec_push(&mut nstate.timestamps,
-            self.start_time.elapsed().as_millis());
+        limited_vec_push(&mut nstate.timestamps, now, self.nr_stats);
+
+        let window = self.leak_window.min(self.nr_stats);
+        for ds in nstate.devs_state.iter_mut() {
+            for cli in ds.clis_stats.iter_mut() {
+                cli.update_mem_growth(&nstate.timestamps, window);
+            }
+        }
 
         self.state = nstate;
 
         Ok(())
     }
 
-    pub fn from(qmds: DrmDevices) -> AppData
+    pub fn toggle_energy_mark(&mut self)
+    {
+        let now = self.start_time.elapsed().as_millis();
+
+        for ds in self.state.devs_state.iter_mut() {
+            if let Some(nrg) = &mut ds.dev_stats.energy {
+                nrg.toggle_mark(now);
+            }
+        }
+    }
+
+    // parses "<class>=<weight>,..." with engine classes (or engine names
+    // not in any class), engines without a weight default to 1.0
+    pub fn set_power_weights(&mut self, weights: &str) -> Result<()>
+    {
+        for cw in weights.split(',') {
+            if let Some((cls, w)) = cw.split_once('=') {
+                let wval: f64 = w.trim().parse()?;
+                if wval < 0.0 {
+                    bail!("Negative power weight for {:?}", cls);
+                }
+                self.power_weights.insert(cls.trim().to_string(), wval);
+            } else {
+                bail!("Invalid power weight {:?}, expected <class>=<weight>",
+                    cw);
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn set_leak_window(&mut self, window: usize) -> Result<()>
     {
-        AppData {
+        if window < 2 {
+            bail!("Leak detection window must be at least 2 updates");
+        }
+        self.leak_window = window;
+
+        Ok(())
+    }
+
+    // history is the number of stats updates kept, if not the default
+    pub fn from(qmds: DrmDevices, history: Option<usize>) -> Result<AppData>
+    {
+        let nr_stats = history.unwrap_or(APP_DATA_DEFAULT_NR_STATS);
+        if !(2..=APP_DATA_MAX_NR_STATS).contains(&nr_stats) {
+            bail!("History length must be between 2 and {} updates",
+                APP_DATA_MAX_NR_STATS);
+        }
+
+        Ok(AppData {
             state: AppDataState::new(),
             qmds,
+            power_weights: HashMap::new(),
+            leak_window: APP_DATA_DEFAULT_LEAK_WINDOW,
+            nr_stats,
             start_time: time::Instant::now(),
-        }
+        })
     }
 }
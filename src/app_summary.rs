@@ -0,0 +1,513 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use itertools::Itertools;
+use serde::{Serialize, Serializer, ser::SerializeStruct};
+
+use crate::app_data::{AppData, AppDataDeviceState, AppDataClientStats};
+
+
+const MIB: f64 = 1024.0 * 1024.0;
+
+// keeps at most this many DRM clients that already exited per device
+const APP_SUMMARY_MAX_EXITED_CLIENTS: usize = 32;
+
+// P-square estimator of a quantile (Jain & Chlamtac, 1985): tracks it with 5
+// markers instead of keeping all the samples, exact up to 5 samples
+#[derive(Debug)]
+struct AppSummaryQuantile
+{
+    pct: f64,
+    count: usize,
+    heights: [f64; 5],
+    pos: [f64; 5],
+    desired: [f64; 5],
+    incr: [f64; 5],
+}
+
+impl AppSummaryQuantile
+{
+    fn parabolic(&self, i: usize, d: f64) -> f64
+    {
+        let (q, n) = (&self.heights, &self.pos);
+
+        q[i] + d / (n[i+1] - n[i-1]) *
+            ((n[i] - n[i-1] + d) * (q[i+1] - q[i]) / (n[i+1] - n[i]) +
+            (n[i+1] - n[i] - d) * (q[i] - q[i-1]) / (n[i] - n[i-1]))
+    }
+
+    fn linear(&self, i: usize, d: f64) -> f64
+    {
+        let (q, n) = (&self.heights, &self.pos);
+        let j = if d > 0.0 { i + 1 } else { i - 1 };
+
+        q[i] + d * (q[j] - q[i]) / (n[j] - n[i])
+    }
+
+    fn add(&mut self, val: f64)
+    {
+        if self.count < 5 {
+            self.heights[self.count] = val;
+            self.count += 1;
+            if self.count == 5 {
+                self.heights.sort_by(|a, b| a.total_cmp(b));
+            }
+            return;
+        }
+        self.count += 1;
+
+        // cell of the new sample, extending the extreme markers if needed
+        let k = if val < self.heights[0] {
+            self.heights[0] = val;
+            0
+        } else if val >= self.heights[4] {
+            self.heights[4] = val;
+            3
+        } else {
+            (0..4).find(|&i| val < self.heights[i+1]).unwrap()
+        };
+
+        for i in k + 1..5 {
+            self.pos[i] += 1.0;
+        }
+        for i in 0..5 {
+            self.desired[i] += self.incr[i];
+        }
+
+        // move the middle markers towards their desired positions
+        for i in 1..4 {
+            let d = self.desired[i] - self.pos[i];
+            if (d >= 1.0 && self.pos[i+1] - self.pos[i] > 1.0) ||
+                (d <= -1.0 && self.pos[i-1] - self.pos[i] < -1.0) {
+                let d = d.signum();
+                let qp = self.parabolic(i, d);
+                self.heights[i] = if self.heights[i-1] < qp &&
+                    qp < self.heights[i+1] { qp } else { self.linear(i, d) };
+                self.pos[i] += d;
+            }
+        }
+    }
+
+    fn value(&self) -> f64
+    {
+        if self.count == 0 {
+            return 0.0;
+        }
+        if self.count >= 5 {
+            return self.heights[2];
+        }
+
+        // nearest-rank on the few samples seen
+        let mut svals = self.heights[..self.count].to_vec();
+        svals.sort_by(|a, b| a.total_cmp(b));
+        let rank = (self.pct / 100.0 * svals.len() as f64).ceil() as usize;
+
+        svals[rank.max(1) - 1]
+    }
+
+    fn new(pct: f64) -> AppSummaryQuantile
+    {
+        let p = pct / 100.0;
+
+        AppSummaryQuantile {
+            pct,
+            count: 0,
+            heights: [0.0; 5],
+            pos: [0.0, 1.0, 2.0, 3.0, 4.0],
+            desired: [0.0, 2.0 * p, 4.0 * p, 2.0 + 2.0 * p, 4.0],
+            incr: [0.0, p / 2.0, p, (1.0 + p) / 2.0, 1.0],
+        }
+    }
+}
+
+// running stats of a stat over the whole run, as the app data only keeps
+// the latest samples
+#[derive(Debug)]
+pub struct AppSummaryStat
+{
+    count: u64,
+    sum: f64,
+    min: f64,
+    max: f64,
+    p95: AppSummaryQuantile,
+}
+
+impl AppSummaryStat
+{
+    pub fn add(&mut self, val: f64)
+    {
+        self.count += 1;
+        self.sum += val;
+        self.min = self.min.min(val);
+        self.max = self.max.max(val);
+        self.p95.add(val);
+    }
+
+    pub fn is_empty(&self) -> bool
+    {
+        self.count == 0
+    }
+
+    pub fn min(&self) -> f64
+    {
+        if self.count == 0 {
+            return 0.0;
+        }
+
+        self.min
+    }
+
+    pub fn avg(&self) -> f64
+    {
+        if self.count == 0 {
+            return 0.0;
+        }
+
+        self.sum / self.count as f64
+    }
+
+    pub fn max(&self) -> f64
+    {
+        if self.count == 0 {
+            return 0.0;
+        }
+
+        self.max
+    }
+
+    // estimated, see AppSummaryQuantile
+    pub fn p95(&self) -> f64
+    {
+        self.p95.value()
+    }
+
+    fn fmt_row(&self, f: &mut fmt::Formatter<'_>,
+        name: &str, scale: f64) -> fmt::Result
+    {
+        writeln!(f, "  {:<20} {:>10.1} {:>10.1} {:>10.1} {:>10.1}", name,
+            self.min() / scale, self.avg() / scale,
+            self.max() / scale, self.p95() / scale)
+    }
+
+    pub fn new() -> AppSummaryStat
+    {
+        AppSummaryStat {
+            count: 0,
+            sum: 0.0,
+            min: f64::MAX,
+            max: f64::MIN,
+            p95: AppSummaryQuantile::new(95.0),
+        }
+    }
+}
+
+impl Serialize for AppSummaryStat
+{
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut st = serializer.serialize_struct("AppSummaryStat", 4)?;
+        st.serialize_field("min", &self.min())?;
+        st.serialize_field("avg", &self.avg())?;
+        st.serialize_field("max", &self.max())?;
+        st.serialize_field("p95", &self.p95())?;
+        st.end()
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct AppSummaryClient
+{
+    pub drm_minor: u32,
+    pub client_id: u32,
+    pub pid: u32,
+    pub comm: String,
+    pub eng_usage: HashMap<String, AppSummaryStat>,
+    pub cpu_usage: AppSummaryStat,
+    pub smem_used: AppSummaryStat,
+    pub vram_used: AppSummaryStat,
+    #[serde(skip_serializing_if = "AppSummaryStat::is_empty")]
+    pub power: AppSummaryStat,
+    #[serde(skip)]
+    exited: bool,
+}
+
+impl AppSummaryClient
+{
+    fn update(&mut self, cli: &AppDataClientStats)
+    {
+        for (en, est) in cli.eng_stats.iter() {
+            self.eng_usage.entry(en.clone())
+                .or_insert_with(AppSummaryStat::new)
+                .add(*est.usage.back().unwrap_or(&0.0));
+        }
+        self.cpu_usage.add(*cli.cpu_usage.back().unwrap_or(&0.0));
+        if let Some(mi) = cli.mem_info.back() {
+            self.smem_used.add(mi.smem_used as f64);
+            self.vram_used.add(mi.vram_used as f64);
+        }
+        if let Some(pwr) = cli.power.back() {
+            self.power.add(*pwr);
+        }
+    }
+
+    fn from(cli: &AppDataClientStats) -> AppSummaryClient
+    {
+        AppSummaryClient {
+            drm_minor: cli.drm_minor,
+            client_id: cli.client_id,
+            pid: cli.pid,
+            comm: cli.comm.clone(),
+            eng_usage: HashMap::new(),
+            cpu_usage: AppSummaryStat::new(),
+            smem_used: AppSummaryStat::new(),
+            vram_used: AppSummaryStat::new(),
+            power: AppSummaryStat::new(),
+            exited: false,
+        }
+    }
+}
+
+impl fmt::Display for AppSummaryClient
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result
+    {
+        writeln!(f, " DRM client [{}] {} (minor {}, id {})",
+            self.pid, self.comm, self.drm_minor, self.client_id)?;
+        for en in self.eng_usage.keys().sorted() {
+            self.eng_usage.get(en).unwrap().fmt_row(f,
+                &format!("{} (%)", en.to_uppercase()), 1.0)?;
+        }
+        self.cpu_usage.fmt_row(f, "CPU (%)", 1.0)?;
+        self.smem_used.fmt_row(f, "SMEM (MiB)", MIB)?;
+        self.vram_used.fmt_row(f, "VRAM (MiB)", MIB)?;
+        if !self.power.is_empty() {
+            self.power.fmt_row(f, "POWER (W)", 1.0)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct AppSummaryDevice
+{
+    pub pci_dev: String,
+    pub vdr_dev_rev: String,
+    pub drv_name: String,
+    pub eng_usage: HashMap<String, AppSummaryStat>,
+    #[serde(skip_serializing_if = "AppSummaryStat::is_empty")]
+    pub act_freq: AppSummaryStat,
+    #[serde(skip_serializing_if = "AppSummaryStat::is_empty")]
+    pub cur_freq: AppSummaryStat,
+    #[serde(skip_serializing_if = "AppSummaryStat::is_empty")]
+    pub gpu_power: AppSummaryStat,
+    #[serde(skip_serializing_if = "AppSummaryStat::is_empty")]
+    pub pkg_power: AppSummaryStat,
+    pub smem_used: AppSummaryStat,
+    pub vram_used: AppSummaryStat,
+    // memory from the DRM clients being tracked, not the whole device
+    pub clis_smem_used: AppSummaryStat,
+    pub clis_vram_used: AppSummaryStat,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub gpu_energy: Option<f64>,
+    pub throttle_ms: u64,
+    pub total_ms: u64,
+    pub clients: Vec<AppSummaryClient>,
+}
+
+impl AppSummaryDevice
+{
+    fn update(&mut self, dinfo: &AppDataDeviceState, ms_elapsed: u64)
+    {
+        self.total_ms += ms_elapsed;
+
+        for en in dinfo.eng_names.iter() {
+            let est = dinfo.dev_stats.eng_stats.get(en).unwrap();
+            self.eng_usage.entry(en.clone())
+                .or_insert_with(AppSummaryStat::new)
+                .add(*est.usage.back().unwrap_or(&0.0));
+        }
+
+        if let Some(fqs) = dinfo.dev_stats.freqs.back() {
+            self.act_freq.add(fqs.act_freq as f64);
+            self.cur_freq.add(fqs.cur_freq as f64);
+            if fqs.throttle_reasons.status {
+                self.throttle_ms += ms_elapsed;
+            }
+        }
+        if let Some(pwr) = dinfo.dev_stats.power.back() {
+            self.gpu_power.add(pwr.gpu_cur_power);
+            self.pkg_power.add(pwr.pkg_cur_power);
+        }
+        if let Some(mi) = dinfo.dev_stats.mem_info.back() {
+            self.smem_used.add(mi.smem_used as f64);
+            self.vram_used.add(mi.vram_used as f64);
+        }
+        if let Some(nrg) = &dinfo.dev_stats.energy {
+            self.gpu_energy = Some(nrg.gpu_energy);
+        }
+
+        let mut smem: u64 = 0;
+        let mut vram: u64 = 0;
+        for scli in self.clients.iter_mut() {
+            scli.exited = true;
+        }
+        for cli in dinfo.clis_stats.iter() {
+            if let Some(mi) = cli.mem_info.back() {
+                smem += mi.smem_used;
+                vram += mi.vram_used;
+            }
+
+            let pos = self.clients.iter().position(|c|
+                c.drm_minor == cli.drm_minor && c.client_id == cli.client_id);
+            let scli = if let Some(idx) = pos {
+                &mut self.clients[idx]
+            } else {
+                self.clients.push(AppSummaryClient::from(cli));
+                self.clients.last_mut().unwrap()
+            };
+            scli.exited = false;
+            scli.update(cli);
+        }
+        self.clis_smem_used.add(smem as f64);
+        self.clis_vram_used.add(vram as f64);
+
+        // drop the oldest clients that went away, their usage still counts
+        // in the device stats
+        let nr_exited = self.clients.iter().filter(|c| c.exited).count();
+        let mut to_drop = nr_exited.saturating_sub(
+            APP_SUMMARY_MAX_EXITED_CLIENTS);
+        self.clients.retain(|c| {
+            if c.exited && to_drop > 0 {
+                to_drop -= 1;
+                return false;
+            }
+            true
+        });
+    }
+
+    fn from(dinfo: &AppDataDeviceState) -> AppSummaryDevice
+    {
+        AppSummaryDevice {
+            pci_dev: dinfo.pci_dev.clone(),
+            vdr_dev_rev: dinfo.vdr_dev_rev.clone(),
+            drv_name: dinfo.drv_name.clone(),
+            eng_usage: HashMap::new(),
+            act_freq: AppSummaryStat::new(),
+            cur_freq: AppSummaryStat::new(),
+            gpu_power: AppSummaryStat::new(),
+            pkg_power: AppSummaryStat::new(),
+            smem_used: AppSummaryStat::new(),
+            vram_used: AppSummaryStat::new(),
+            clis_smem_used: AppSummaryStat::new(),
+            clis_vram_used: AppSummaryStat::new(),
+            gpu_energy: None,
+            throttle_ms: 0,
+            total_ms: 0,
+            clients: Vec::new(),
+        }
+    }
+}
+
+impl fmt::Display for AppSummaryDevice
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result
+    {
+        writeln!(f, "{} {} ({})", self.pci_dev, self.vdr_dev_rev, self.drv_name)?;
+        writeln!(f, "  {:<20} {:>10} {:>10} {:>10} {:>10}",
+            "", "min", "avg", "max", "p95")?;
+
+        for en in self.eng_usage.keys().sorted() {
+            self.eng_usage.get(en).unwrap().fmt_row(f,
+                &format!("{} (%)", en.to_uppercase()), 1.0)?;
+        }
+        if !self.act_freq.is_empty() {
+            self.act_freq.fmt_row(f, "Actual freq (MHz)", 1.0)?;
+            self.cur_freq.fmt_row(f, "Requested freq (MHz)", 1.0)?;
+        }
+        if !self.gpu_power.is_empty() {
+            self.gpu_power.fmt_row(f, "GPU power (W)", 1.0)?;
+            self.pkg_power.fmt_row(f, "PKG power (W)", 1.0)?;
+        }
+        self.smem_used.fmt_row(f, "SMEM (MiB)", MIB)?;
+        self.vram_used.fmt_row(f, "VRAM (MiB)", MIB)?;
+        self.clis_smem_used.fmt_row(f, "Clients SMEM (MiB)", MIB)?;
+        self.clis_vram_used.fmt_row(f, "Clients VRAM (MiB)", MIB)?;
+
+        if let Some(nrg) = self.gpu_energy {
+            writeln!(f, "  GPU energy: {:.1} J ({:.4} Wh)", nrg, nrg / 3600.0)?;
+        }
+        if !self.act_freq.is_empty() {
+            writeln!(f, "  Throttled: {:.1} s ({:.1}%)",
+                self.throttle_ms as f64 / 1000.0,
+                if self.total_ms > 0 {
+                    self.throttle_ms as f64 * 100.0 / self.total_ms as f64
+                } else { 0.0 })?;
+        }
+
+        for scli in self.clients.iter() {
+            write!(f, "{}", scli)?;
+        }
+        if self.clients.is_empty() {
+            writeln!(f, " No DRM clients")?;
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct AppSummary
+{
+    pub nr_samples: u64,
+    pub devices: Vec<AppSummaryDevice>,
+    #[serde(skip)]
+    last_ts: u128,
+}
+
+impl AppSummary
+{
+    pub fn update(&mut self, data: &AppData)
+    {
+        let now = *data.timestamps().back().unwrap_or(&0);
+        let ms_elapsed = (now - self.last_ts) as u64;
+        self.last_ts = now;
+        self.nr_samples += 1;
+
+        for dinfo in data.devices().iter() {
+            let pos = self.devices.iter()
+                .position(|d| d.pci_dev == dinfo.pci_dev);
+            let sdev = if let Some(idx) = pos {
+                &mut self.devices[idx]
+            } else {
+                self.devices.push(AppSummaryDevice::from(dinfo));
+                self.devices.last_mut().unwrap()
+            };
+
+            sdev.update(dinfo, ms_elapsed);
+        }
+    }
+
+    pub fn new() -> AppSummary
+    {
+        AppSummary {
+            nr_samples: 0,
+            devices: Vec::new(),
+            last_ts: 0,
+        }
+    }
+}
+
+impl fmt::Display for AppSummary
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result
+    {
+        for sdev in self.devices.iter() {
+            write!(f, "{}", sdev)?;
+        }
+
+        Ok(())
+    }
+}
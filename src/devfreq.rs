@@ -0,0 +1,192 @@
+use std::path::{Path, PathBuf};
+use std::fs;
+
+use anyhow::Result;
+use log::debug;
+
+use crate::drm_devices::{
+    DrmDeviceFreqLimits, DrmDeviceFreqs, DrmDeviceDevfreq
+};
+
+
+#[derive(Debug)]
+pub struct Devfreq
+{
+    pub base_dir: PathBuf,
+}
+
+impl Devfreq
+{
+    // devfreq frequencies are in Hz, report them in MHz
+    fn read_mhz(&self, fname: &str) -> Result<u64>
+    {
+        let val: u64 = fs::read_to_string(self.base_dir.join(fname))?
+            .trim().parse()?;
+
+        Ok(val / 1000000)
+    }
+
+    pub fn cur_freq(&self) -> Result<u64>
+    {
+        self.read_mhz("cur_freq")
+    }
+
+    pub fn target_freq(&self) -> Result<u64>
+    {
+        self.read_mhz("target_freq")
+    }
+
+    pub fn min_freq(&self) -> Result<u64>
+    {
+        self.read_mhz("min_freq")
+    }
+
+    pub fn max_freq(&self) -> Result<u64>
+    {
+        self.read_mhz("max_freq")
+    }
+
+    pub fn governor(&self) -> Result<String>
+    {
+        Ok(fs::read_to_string(self.base_dir.join("governor"))?
+            .trim().to_string())
+    }
+
+    pub fn available_frequencies(&self) -> Result<Vec<u64>>
+    {
+        let fstr = fs::read_to_string(
+            self.base_dir.join("available_frequencies"))?;
+
+        let mut res = Vec::new();
+        for fq in fstr.split_whitespace() {
+            let val: u64 = fq.parse()?;
+            res.push(val / 1000000);
+        }
+        res.sort();
+
+        Ok(res)
+    }
+
+    // parses the time in state (in ms) per frequency and the total number
+    // of transitions, ignoring the from/to transitions table itself
+    pub fn trans_stat(&self) -> Result<(Vec<(u64, u64)>, u64)>
+    {
+        let fstr = fs::read_to_string(self.base_dir.join("trans_stat"))?;
+        let mut tis = Vec::new();
+        let mut total: u64 = 0;
+
+        for line in fstr.lines() {
+            let tl = line.trim();
+            if tl.starts_with("Total transition") {
+                if let Some((_, val)) = tl.split_once(':') {
+                    total = val.trim().parse()?;
+                }
+                continue;
+            }
+
+            let tl = tl.trim_start_matches('*').trim();
+            if let Some((fq, vals)) = tl.split_once(':') {
+                // header lines don't start with a frequency
+                if let Ok(fq) = fq.trim().parse::<u64>() {
+                    if let Some(tm) = vals.split_whitespace().last() {
+                        tis.push((fq / 1000000, tm.parse()?));
+                    }
+                }
+            }
+        }
+        tis.sort();
+
+        Ok((tis, total))
+    }
+
+    pub fn freq_limits(&self) -> Result<DrmDeviceFreqLimits>
+    {
+        let mut fls = DrmDeviceFreqLimits::new();
+
+        let avail = self.available_frequencies().unwrap_or_default();
+        if !avail.is_empty() {
+            fls.minimum = avail[0];
+            fls.maximum = avail[avail.len() - 1];
+        } else {
+            fls.minimum = self.min_freq()?;
+            fls.maximum = self.max_freq()?;
+        }
+
+        Ok(fls)
+    }
+
+    pub fn freqs(&self) -> Result<DrmDeviceFreqs>
+    {
+        let mut freqs = DrmDeviceFreqs::new();
+
+        freqs.min_freq = self.min_freq()?;
+        freqs.max_freq = self.max_freq()?;
+        freqs.act_freq = self.cur_freq()?;
+        // target_freq is only exposed by newer kernels
+        freqs.cur_freq = self.target_freq().unwrap_or(freqs.act_freq);
+
+        Ok(freqs)
+    }
+
+    pub fn stats(&self) -> Result<DrmDeviceDevfreq>
+    {
+        let mut dfs = DrmDeviceDevfreq::new();
+
+        dfs.governor = self.governor().unwrap_or_default();
+        dfs.cur_freq = self.cur_freq()?;
+        dfs.min_freq = self.min_freq()?;
+        dfs.max_freq = self.max_freq()?;
+        dfs.available_freqs = self.available_frequencies().unwrap_or_default();
+        // trans_stat can be unsupported (or too big) for some devices
+        if let Ok((tis, total)) = self.trans_stat() {
+            dfs.time_in_state = tis;
+            dfs.total_trans = total;
+        }
+
+        Ok(dfs)
+    }
+
+    pub fn from_dev_dir(dev_dir: &Path) -> Result<Option<Devfreq>>
+    {
+        let base_dir = dev_dir.join("devfreq");
+        if !base_dir.is_dir() {
+            debug!("INF: no {:?} directory, aborting.", base_dir);
+            return Ok(None);
+        }
+
+        let df_path = fs::read_dir(base_dir)?
+            .filter_map(|r| r.ok())
+            .map(|r| r.path())
+            .find(|r| r.join("cur_freq").exists());
+        if df_path.is_none() {
+            debug!("INF: no {:?}/devfreq/* device, aborting.", dev_dir);
+            return Ok(None);
+        }
+
+        Ok(Some(Devfreq {
+            base_dir: df_path.unwrap(),
+        }))
+    }
+
+    pub fn from_name_suffix(suffix: &str) -> Result<Option<Devfreq>>
+    {
+        let class_dir = Path::new("/sys/class/devfreq");
+        if !class_dir.is_dir() {
+            return Ok(None);
+        }
+
+        let df_path = fs::read_dir(class_dir)?
+            .filter_map(|r| r.ok())
+            .map(|r| r.path())
+            .find(|r| r.file_name().unwrap()
+                .to_str().unwrap().ends_with(suffix));
+        if df_path.is_none() {
+            debug!("INF: no devfreq device named *{:?}, aborting.", suffix);
+            return Ok(None);
+        }
+
+        Ok(Some(Devfreq {
+            base_dir: df_path.unwrap(),
+        }))
+    }
+}
@@ -1,4 +1,5 @@
 use std::collections::{VecDeque, HashMap};
+use std::cmp::max;
 use std::cell::{RefCell, RefMut};
 use std::path::PathBuf;
 use std::rc::{Rc, Weak};
@@ -154,7 +155,8 @@ impl DrmClientInfo
         let cap = self.engs_last.get(eng).unwrap().capacity as f64;
 
         let mut res: f64 = 0.0;
-        if acum.acum_cycles > 0 {
+        // some drivers report cycles without total cycles, use time then
+        if acum.acum_cycles > 0 && acum.acum_total_cycles > 0 {
             res = (ed.delta_cycles as f64 * 100.0) /
                 (ed.delta_total_cycles as f64 * cap);
         } else if acum.acum_time > 0 {
@@ -170,6 +172,29 @@ impl DrmClientInfo
         res
     }
 
+    // number of engine instances backing the engine name (e.g. 4 for a
+    // CCS engine class with 4 compute engines), always at least 1
+    pub fn eng_capacity(&self, eng: &String) -> u32
+    {
+        if let Some(el) = self.engs_last.get(eng) {
+            return max(el.capacity, 1);
+        }
+
+        1
+    }
+
+    // current and max frequencies (MHz) of the engine, for drivers that
+    // report them through drm-curfreq-<engine> and drm-maxfreq-<engine>
+    pub fn eng_freqs(&self, eng: &String) -> Option<(u64, u64)>
+    {
+        let el = self.engs_last.get(eng)?;
+        if el.max_freq == 0 {
+            return None;
+        }
+
+        Some((el.cur_freq, el.max_freq))
+    }
+
     pub fn engines(&self) -> Vec<&String>
     {
         let mut res: Vec<&String> = self.engs_delta.keys().collect::<Vec<&_>>();
@@ -237,6 +262,9 @@ impl DrmClientInfo
             let deng = self.engs_delta.get_mut(nm).unwrap();
             let neng = fdi.engines.get(nm).unwrap();
 
+            oeng.capacity = neng.capacity;
+            oeng.cur_freq = neng.cur_freq;
+            oeng.max_freq = neng.max_freq;
             if neng.time >= oeng.time {
                 self.engs_acum.acum_time += neng.time;
                 deng.delta_time = neng.time - oeng.time;
@@ -1,6 +1,11 @@
+use core::fmt::Debug;
 use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::fs;
 use std::cell::RefCell;
 use std::rc::{Rc, Weak};
+use std::mem;
+use std::cmp::max;
 
 use anyhow::{bail, Result};
 use libc;
@@ -8,10 +13,15 @@ use log::{debug, warn};
 use serde::{Deserialize, Serialize};
 use udev;
 
+use crate::pci_ids::PciIds;
 use crate::drm_clients::{DrmClients, DrmClientInfo};
 use crate::drm_drivers::{self, DrmDriver};
 
 
+// char device majors of DRM (/dev/dri) and compute accel (/dev/accel) nodes
+pub const DRM_MAJOR: u32 = 226;
+pub const ACCEL_MAJOR: u32 = 261;
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum DrmDeviceType
 {
@@ -74,6 +84,49 @@ impl DrmDeviceThrottleReasons
             status: false,
         }
     }
+
+    pub fn reasons(&self) -> [(&'static str, bool); 9]
+    {
+        [
+            ("status", self.status),
+            ("pl1", self.pl1),
+            ("pl2", self.pl2),
+            ("pl4", self.pl4),
+            ("prochot", self.prochot),
+            ("ratl", self.ratl),
+            ("thermal", self.thermal),
+            ("vr_tdc", self.vr_tdc),
+            ("vr_thermalert", self.vr_thermalert),
+        ]
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DrmDeviceCapabilities
+{
+    pub smem_used: bool,
+    pub vram: bool,
+    pub freqs: bool,
+    pub power: bool,
+    pub temps: bool,
+    pub fans: bool,
+    pub client_mem_info: bool,
+}
+
+impl DrmDeviceCapabilities
+{
+    pub fn new() -> DrmDeviceCapabilities
+    {
+        DrmDeviceCapabilities {
+            smem_used: false,
+            vram: false,
+            freqs: false,
+            power: false,
+            temps: false,
+            fans: false,
+            client_mem_info: false,
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -82,6 +135,8 @@ pub struct DrmDeviceFreqLimits
     pub minimum: u64,
     pub efficient: u64,
     pub maximum: u64,
+    pub mem_minimum: u64,
+    pub mem_maximum: u64,
 }
 
 impl DrmDeviceFreqLimits
@@ -92,6 +147,8 @@ impl DrmDeviceFreqLimits
             minimum: 0,
             efficient: 0,
             maximum: 0,
+            mem_minimum: 0,
+            mem_maximum: 0,
         }
     }
 }
@@ -103,6 +160,7 @@ pub struct DrmDeviceFreqs
     pub cur_freq: u64,
     pub act_freq: u64,
     pub max_freq: u64,
+    pub mem_freq: u64,
     pub throttle_reasons: DrmDeviceThrottleReasons,
 }
 
@@ -115,16 +173,76 @@ impl DrmDeviceFreqs
             cur_freq: 0,
             act_freq: 0,
             max_freq: 0,
+            mem_freq: 0,
             throttle_reasons: DrmDeviceThrottleReasons::new(),
         }
     }
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DrmDeviceDevfreq
+{
+    pub governor: String,
+    pub cur_freq: u64,
+    pub min_freq: u64,
+    pub max_freq: u64,
+    pub available_freqs: Vec<u64>,
+    pub time_in_state: Vec<(u64, u64)>,  // (freq in MHz, time in ms)
+    pub total_trans: u64,
+}
+
+impl DrmDeviceDevfreq
+{
+    pub fn new() -> DrmDeviceDevfreq
+    {
+        DrmDeviceDevfreq {
+            governor: String::new(),
+            cur_freq: 0,
+            min_freq: 0,
+            max_freq: 0,
+            available_freqs: Vec::new(),
+            time_in_state: Vec::new(),
+            total_trans: 0,
+        }
+    }
+}
+
+// activity and clocks of GPU blocks other than the engines, for drivers
+// with firmware reporting them (e.g. amdgpu SMU metrics)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DrmDeviceBlockStats
+{
+    pub gfx_activity: Option<f64>,      // %
+    pub mem_activity: Option<f64>,      // %
+    pub mm_activity: Option<f64>,       // % (multimedia)
+    pub soc_freq: Option<u64>,          // MHz
+    pub vclk_freq: Option<u64>,         // MHz (video)
+    pub dclk_freq: Option<u64>,         // MHz (video decode)
+}
+
+impl DrmDeviceBlockStats
+{
+    pub fn new() -> DrmDeviceBlockStats
+    {
+        DrmDeviceBlockStats {
+            gfx_activity: None,
+            mem_activity: None,
+            mm_activity: None,
+            soc_freq: None,
+            vclk_freq: None,
+            dclk_freq: None,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DrmDevicePower
 {
     pub gpu_cur_power: f64,
     pub pkg_cur_power: f64,
+    // energy (J) since last update, for drivers reading energy counters
+    pub gpu_energy: f64,
+    pub pkg_energy: f64,
 }
 
 impl DrmDevicePower
@@ -134,6 +252,82 @@ impl DrmDevicePower
         DrmDevicePower {
             gpu_cur_power: 0.0,
             pkg_cur_power: 0.0,
+            gpu_energy: 0.0,
+            pkg_energy: 0.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DrmDevicePowerLimit
+{
+    pub name: String,
+    pub limit: f64,
+}
+
+impl DrmDevicePowerLimit
+{
+    pub fn from(name: &str, limit: f64) -> DrmDevicePowerLimit
+    {
+        DrmDevicePowerLimit {
+            name: name.to_string(),
+            limit,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DrmDeviceTemperature
+{
+    pub name: String,
+    pub temp: f64,
+    pub max: f64,
+    pub crit: f64,
+}
+
+impl DrmDeviceTemperature
+{
+    pub fn new() -> DrmDeviceTemperature
+    {
+        DrmDeviceTemperature {
+            name: String::new(),
+            temp: 0.0,
+            max: 0.0,
+            crit: 0.0,
+        }
+    }
+
+    // closest threshold to throttle/shutdown, if any is known
+    pub fn limit(&self) -> f64
+    {
+        if self.crit > 0.0 {
+            self.crit
+        } else {
+            self.max
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DrmDeviceFan
+{
+    pub name: String,
+    pub speed: u64,
+    pub target: u64,
+    pub max: u64,
+    pub duty: f64,
+}
+
+impl DrmDeviceFan
+{
+    pub fn new() -> DrmDeviceFan
+    {
+        DrmDeviceFan {
+            name: String::new(),
+            speed: 0,
+            target: 0,
+            max: 0,
+            duty: 0.0,
         }
     }
 }
@@ -160,11 +354,177 @@ impl DrmDeviceMemInfo
     }
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DrmDeviceFirmware
+{
+    pub name: String,
+    pub version: String,
+}
+
+impl DrmDeviceFirmware
+{
+    pub fn from(name: &str, version: &str) -> DrmDeviceFirmware
+    {
+        DrmDeviceFirmware {
+            name: name.to_string(),
+            version: version.to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DrmDevicePciInfo
+{
+    pub cur_link_speed: String,
+    pub cur_link_width: String,
+    pub max_link_speed: String,
+    pub max_link_width: String,
+    pub numa_node: i32,
+    pub iommu_group: String,
+}
+
+impl DrmDevicePciInfo
+{
+    fn read_attr(dev_dir: &Path, fname: &str) -> String
+    {
+        if let Ok(val) = fs::read_to_string(dev_dir.join(fname)) {
+            return val.trim().to_string();
+        }
+
+        String::new()
+    }
+
+    pub fn from_pci_dev(pci_dev: &str) -> DrmDevicePciInfo
+    {
+        let dev_dir = Path::new("/sys/bus/pci/devices").join(pci_dev);
+
+        let iommu_group = if let Ok(grp) = fs::read_link(
+            dev_dir.join("iommu_group")) {
+            grp.file_name().unwrap().to_str().unwrap().to_string()
+        } else {
+            String::new()
+        };
+
+        DrmDevicePciInfo {
+            cur_link_speed: DrmDevicePciInfo::read_attr(
+                &dev_dir, "current_link_speed"),
+            cur_link_width: DrmDevicePciInfo::read_attr(
+                &dev_dir, "current_link_width"),
+            max_link_speed: DrmDevicePciInfo::read_attr(
+                &dev_dir, "max_link_speed"),
+            max_link_width: DrmDevicePciInfo::read_attr(
+                &dev_dir, "max_link_width"),
+            numa_node: DrmDevicePciInfo::read_attr(&dev_dir, "numa_node")
+                .parse().unwrap_or(-1),
+            iommu_group,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DrmDeviceVfProvisioning
+{
+    pub vf_id: u32,
+    // None if missing or unparsable
+    pub ggtt_quota: Option<u64>,
+    pub lmem_quota: Option<u64>,
+    pub contexts_quota: Option<u64>,
+    pub doorbells_quota: Option<u64>,
+    pub exec_quantum_ms: Option<u64>,
+    pub preempt_timeout_us: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DrmDeviceSriov
+{
+    pub pf_dev: String,         // only on VFs
+    pub vf_id: u32,             // only on VFs, 1-based
+    pub total_vfs: u32,         // only on PFs
+    pub vf_devs: Vec<String>,   // only on PFs, enabled VFs
+    pub vf_provs: Vec<DrmDeviceVfProvisioning>,
+}
+
+impl DrmDeviceSriov
+{
+    pub fn is_pf(&self) -> bool
+    {
+        self.pf_dev.is_empty()
+    }
+
+    pub fn is_vf(&self) -> bool
+    {
+        !self.pf_dev.is_empty()
+    }
+
+    fn link_name(lpath: &Path) -> Option<String>
+    {
+        let dev = fs::canonicalize(lpath).ok()?;
+
+        Some(dev.file_name()?.to_str()?.to_string())
+    }
+
+    // PFs have sriov_totalvfs and virtfnN links to enabled VFs,
+    // VFs have a physfn link back to their PF
+    pub fn from_pci_dev(pci_dev: &str) -> Option<DrmDeviceSriov>
+    {
+        let dev_dir = Path::new("/sys/bus/pci/devices").join(pci_dev);
+
+        if let Some(pf_dev) = DrmDeviceSriov::link_name(
+            &dev_dir.join("physfn")) {
+            let pf_dir = dev_dir.join("physfn");
+            let mut vf_id = 0;
+            for idx in 0.. {
+                let vfn = pf_dir.join(format!("virtfn{}", idx));
+                if !vfn.exists() {
+                    break;
+                }
+                if DrmDeviceSriov::link_name(&vfn).as_deref() == Some(pci_dev) {
+                    vf_id = idx + 1;
+                    break;
+                }
+            }
+
+            return Some(DrmDeviceSriov {
+                pf_dev,
+                vf_id,
+                total_vfs: 0,
+                vf_devs: Vec::new(),
+                vf_provs: Vec::new(),
+            });
+        }
+
+        let total_vfs: u32 = fs::read_to_string(
+            dev_dir.join("sriov_totalvfs")).ok()?.trim().parse().ok()?;
+        if total_vfs == 0 {
+            return None;
+        }
+
+        let mut vf_devs = Vec::new();
+        for idx in 0..total_vfs {
+            if let Some(vf) = DrmDeviceSriov::link_name(
+                &dev_dir.join(format!("virtfn{}", idx))) {
+                vf_devs.push(vf);
+            } else {
+                break;
+            }
+        }
+
+        Some(DrmDeviceSriov {
+            pf_dev: String::new(),
+            vf_id: 0,
+            total_vfs,
+            vf_devs,
+            vf_provs: Vec::new(),
+        })
+    }
+}
+
 #[derive(Debug)]
 #[allow(dead_code)]
 pub struct DrmMinorInfo
 {
     pub devnode: String,
+    pub drm_major: u32,
     pub drm_minor: u32,
 }
 
@@ -180,16 +540,22 @@ impl DrmMinorInfo
             mn = libc::minor(devnum);
         }
 
-        if mj != 226 {
-            bail!("Expected DRM major 226 but found {:?} for {:?}",
-                mj, devnode);
+        if mj != DRM_MAJOR && mj != ACCEL_MAJOR {
+            bail!("Expected DRM major {} or accel major {} but found {:?} for {:?}",
+                DRM_MAJOR, ACCEL_MAJOR, mj, devnode);
         }
 
         Ok(DrmMinorInfo {
             devnode: devnode.clone(),
+            drm_major: mj,
             drm_minor: mn,
         })
     }
+
+    pub fn is_accel(&self) -> bool
+    {
+        self.drm_major == ACCEL_MAJOR
+    }
 }
 
 #[derive(Debug)]
@@ -202,13 +568,27 @@ pub struct DrmDeviceInfo
     pub device_id: String,
     pub device: String,
     pub revision: String,
+    pub subsys_vendor_id: String,
+    pub subsys_vendor: String,
+    pub subsys_device_id: String,
+    pub subsys_device: String,
     pub drv_name: String,
+    pub drv_version: String,
     pub drm_minors: Vec<DrmMinorInfo>,
     pub dev_type: DrmDeviceType,
+    pub caps: DrmDeviceCapabilities,
     pub freq_limits: DrmDeviceFreqLimits,
     pub freqs: DrmDeviceFreqs,
+    pub devfreq: Option<DrmDeviceDevfreq>,
+    pub block_stats: Option<DrmDeviceBlockStats>,
     pub power: DrmDevicePower,
+    pub power_limits: Vec<DrmDevicePowerLimit>,
     pub mem_info: DrmDeviceMemInfo,
+    pub temps: Vec<DrmDeviceTemperature>,
+    pub fans: Vec<DrmDeviceFan>,
+    pub firmwares: Vec<DrmDeviceFirmware>,
+    pub pci_info: Option<DrmDevicePciInfo>,
+    pub sriov: Option<DrmDeviceSriov>,
     driver: Option<Rc<RefCell<dyn DrmDriver>>>,
     drm_clis: Option<Rc<RefCell<Vec<DrmClientInfo>>>>,
 }
@@ -224,13 +604,27 @@ impl Default for DrmDeviceInfo
             device_id: String::new(),
             device: String::new(),
             revision: String::new(),
+            subsys_vendor_id: String::new(),
+            subsys_vendor: String::new(),
+            subsys_device_id: String::new(),
+            subsys_device: String::new(),
             drv_name: String::new(),
+            drv_version: String::new(),
             drm_minors: Vec::new(),
             dev_type: DrmDeviceType::Unknown,
+            caps: DrmDeviceCapabilities::new(),
             freq_limits: DrmDeviceFreqLimits::new(),
             freqs: DrmDeviceFreqs::new(),
+            devfreq: None,
+            block_stats: None,
             power: DrmDevicePower::new(),
+            power_limits: Vec::new(),
             mem_info: DrmDeviceMemInfo::new(),
+            temps: Vec::new(),
+            fans: Vec::new(),
+            firmwares: Vec::new(),
+            pci_info: None,
+            sriov: None,
             driver: None,
             drm_clis: None,
         }
@@ -239,6 +633,11 @@ impl Default for DrmDeviceInfo
 
 impl DrmDeviceInfo
 {
+    pub fn is_pci(&self) -> bool
+    {
+        !self.vendor_id.is_empty()
+    }
+
     // relies on DRM clients list for now
     // (could store after each refresh and read from driver later, if needed)
     pub fn eng_utilization(&self, eng: &String) -> f64
@@ -262,6 +661,23 @@ impl DrmDeviceInfo
         0.0
     }
 
+    // relies on DRM clients list for now
+    // (could store after each refresh and read from driver later, if needed)
+    pub fn eng_capacity(&self, eng: &String) -> u32
+    {
+        let mut res: u32 = 1;
+
+        if let Some(vref) = &self.drm_clis {
+            let clis_b = vref.borrow();
+
+            for cli in clis_b.iter() {
+                res = max(res, cli.eng_capacity(eng));
+            }
+        }
+
+        res
+    }
+
     // relies on DRM clients list for now
     // (could store after each refresh and read from driver later, if needed)
     pub fn engines(&self) -> Vec<String>
@@ -301,21 +717,74 @@ impl DrmDeviceInfo
         if let Some(drv_ref) = &self.driver {
             let mut drv_b = drv_ref.borrow_mut();
 
-            // note: dev_type and freq_limits don't change
+            // note: dev_type, caps, freq_limits and power_limits don't change
             self.freqs = drv_b.freqs()?;
+            self.devfreq = drv_b.devfreq()?;
+            self.block_stats = drv_b.block_stats()?;
             self.power = drv_b.power()?;
             self.mem_info = drv_b.mem_info()?;
+            self.temps = drv_b.temps()?;
+            self.fans = drv_b.fans()?;
+        }
+
+        // PCIe link can be retrained and VFs can be enabled/disabled on PFs
+        // at any time
+        if self.is_pci() {
+            self.pci_info = Some(DrmDevicePciInfo::from_pci_dev(&self.pci_dev));
+            self.sriov = DrmDeviceSriov::from_pci_dev(&self.pci_dev);
+        }
+        if let Some(sriov) = &mut self.sriov {
+            if sriov.is_pf() && !sriov.vf_devs.is_empty() {
+                if let Some(drv_ref) = &self.driver {
+                    let mut drv_b = drv_ref.borrow_mut();
+                    sriov.vf_provs = drv_b.vf_provisioning(
+                        sriov.vf_devs.len() as u32)?;
+                }
+            }
         }
 
         Ok(())
     }
 }
 
+struct DrmDevicesMonitor
+{
+    socket: udev::MonitorSocket,
+    pending: Vec<PathBuf>,
+}
+
+impl Debug for DrmDevicesMonitor
+{
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "DrmDevicesMonitor(pending: {:?})", self.pending)
+    }
+}
+
+impl DrmDevicesMonitor
+{
+    fn new() -> Result<DrmDevicesMonitor>
+    {
+        let socket = udev::MonitorBuilder::new()?
+            .match_subsystem("drm")?
+            .match_subsystem("accel")?
+            .listen()?;
+
+        Ok(DrmDevicesMonitor {
+            socket,
+            pending: Vec::new(),
+        })
+    }
+}
+
 #[derive(Debug)]
 pub struct DrmDevices
 {
     infos: HashMap<String, DrmDeviceInfo>,
     qmclis: Option<DrmClients>,
+    monitor: Option<DrmDevicesMonitor>,
+    pci_ids_path: Option<String>,
+    pci_ids: Option<PciIds>,
+    pci_ids_tried: bool,
 }
 
 impl DrmDevices
@@ -340,6 +809,9 @@ impl DrmDevices
 
     pub fn refresh(&mut self) -> Result<()>
     {
+        // add/remove hot-plugged/unplugged devices (if any)
+        self.handle_hotplug()?;
+
         // update DRM clients information (if possible)
         if let Some(clis) = &mut self.qmclis {
             clis.refresh()?;
@@ -353,8 +825,8 @@ impl DrmDevices
             }
         }
 
-        // assumes devices don't vanish, so just update their driver-specific
-        // dynamic information (e.g. mem info, engines, freqs, power)
+        // update devices driver-specific dynamic information
+        // (e.g. mem info, engines, freqs, power, temps, fans)
         for di in self.infos.values_mut() {
             di.refresh()?;
         }
@@ -376,10 +848,25 @@ impl DrmDevices
         DrmDevices {
             infos: HashMap::new(),
             qmclis: None,
+            monitor: None,
+            pci_ids_path: None,
+            pci_ids: None,
+            pci_ids_tried: false,
         }
     }
 
-    fn find_vendor(vendor_id: &String) -> String
+    // pci.ids is only parsed (once) if the udev hwdb doesn't have a name
+    fn pci_ids(&mut self) -> Option<&PciIds>
+    {
+        if !self.pci_ids_tried {
+            self.pci_ids = PciIds::find(self.pci_ids_path.as_deref());
+            self.pci_ids_tried = true;
+        }
+
+        self.pci_ids.as_ref()
+    }
+
+    fn find_vendor(&mut self, vendor_id: &String) -> String
     {
         if let Ok(hwdb) = udev::Hwdb::new() {
             let id = u32::from_str_radix(vendor_id, 16).unwrap();
@@ -391,10 +878,15 @@ impl DrmDevices
             }
         }
 
+        if let Some(res) = self.pci_ids()
+            .and_then(|pids| pids.vendor_name(vendor_id)) {
+            return res.to_string();
+        }
+
         vendor_id.clone()
     }
 
-    fn find_device(vendor_id: &String, device_id: &String) -> String
+    fn find_device(&mut self, vendor_id: &String, device_id: &String) -> String
     {
         if let Ok(hwdb) = udev::Hwdb::new() {
             let vid = u32::from_str_radix(vendor_id, 16).unwrap();
@@ -407,74 +899,360 @@ impl DrmDevices
             }
         }
 
+        if let Some(res) = self.pci_ids()
+            .and_then(|pids| pids.device_name(vendor_id, device_id)) {
+            return res.to_string();
+        }
+
         device_id.clone()
     }
 
-    pub fn find_devices() -> Result<DrmDevices>
+    // returns an empty string if there's no name for that subsystem
+    fn find_subsystem(&mut self, vendor_id: &str, device_id: &str,
+        subsys_vendor_id: &str, subsys_device_id: &str) -> String
     {
-        let mut qmds = DrmDevices::new();
+        if let Ok(hwdb) = udev::Hwdb::new() {
+            let vid = u32::from_str_radix(vendor_id, 16).unwrap();
+            let did = u32::from_str_radix(device_id, 16).unwrap();
+            let svid = u32::from_str_radix(subsys_vendor_id, 16).unwrap();
+            let sdid = u32::from_str_radix(subsys_device_id, 16).unwrap();
+            let dev_ma = format!("pci:v{:08X}d{:08X}*", vid, did);
+            let subsys_ma = format!("pci:v{:08X}d{:08X}sv{:08X}sd{:08X}*",
+                vid, did, svid, sdid);
+
+            // falls back to the device entry if there's no subsystem one
+            let prop = "ID_MODEL_FROM_DATABASE".to_string();
+            if let Some(res) = hwdb.query_one(subsys_ma, prop.clone()) {
+                if hwdb.query_one(dev_ma, prop).as_ref() != Some(&res) {
+                    return res.to_str().unwrap().to_string();
+                }
+            }
+        }
 
-        let mut enumerator = udev::Enumerator::new()?;
-        enumerator.match_subsystem("drm")?;
-        enumerator.match_property("DEVNAME", "/dev/dri/*")?;
+        if let Some(res) = self.pci_ids().and_then(|pids|
+            pids.subsystem_name(vendor_id, device_id,
+                subsys_vendor_id, subsys_device_id)) {
+            return res.to_string();
+        }
 
-        for d in enumerator.scan_devices()? {
-            let pdev = d.parent().unwrap();
-            let sysname = String::from(pdev.sysname().to_str().unwrap());
+        String::new()
+    }
 
-            if !qmds.infos.contains_key(&sysname) {
-                let pciid = if let Some(pciid) = pdev.property_value("PCI_ID") {
-                    pciid.to_str().unwrap()
-                } else {
-                    debug!("INF: Ignoring device without PCI_ID: {:?}",
-                        pdev.syspath());
-                    continue;
-                };
+    fn platform_names(pdev: &udev::Device) -> (String, String)
+    {
+        // device tree compatible is usually "vendor,device"
+        if let Some(compat) = pdev.property_value("OF_COMPATIBLE_0") {
+            let compat = compat.to_str().unwrap();
+            if let Some((vdr, dev)) = compat.split_once(',') {
+                return (String::from(vdr), String::from(dev));
+            }
+            return (String::new(), String::from(compat));
+        }
 
-                let vendor_id = String::from(&pciid[0..4]);
-                let vendor = DrmDevices::find_vendor(&vendor_id);
-                let device_id = String::from(&pciid[5..9]);
-                let device = DrmDevices::find_device(&vendor_id, &device_id);
-                let revision = pdev.attribute_value("revision")
+        (String::new(), String::from(pdev.sysname().to_str().unwrap()))
+    }
+
+    // adds DRM/accel minor from udev device to its (maybe new) parent
+    // device, returns the parent device sysname if the minor was added
+    fn add_minor(&mut self, d: &udev::Device) -> Result<Option<String>>
+    {
+        let pdev = if let Some(pd) = d.parent() {
+            pd
+        } else {
+            return Ok(None);
+        };
+        let sysname = String::from(pdev.sysname().to_str().unwrap());
+
+        if !self.infos.contains_key(&sysname) {
+            let drv_name = if let Some(drv) = pdev.driver() {
+                String::from(drv.to_str().unwrap())
+            } else {
+                debug!("INF: Ignoring device without driver: {:?}",
+                    pdev.syspath());
+                return Ok(None);
+            };
+
+            let vendor_id: String;
+            let vendor: String;
+            let device_id: String;
+            let device: String;
+            let revision: String;
+            let mut subsys_vendor_id = String::new();
+            let mut subsys_vendor = String::new();
+            let mut subsys_device_id = String::new();
+            let mut subsys_device = String::new();
+            if let Some(pciid) = pdev.property_value("PCI_ID") {
+                let pciid = pciid.to_str().unwrap();
+                vendor_id = String::from(&pciid[0..4]);
+                vendor = self.find_vendor(&vendor_id);
+                device_id = String::from(&pciid[5..9]);
+                device = self.find_device(&vendor_id, &device_id);
+                let rev = pdev.attribute_value("revision")
                     .unwrap().to_str().unwrap();
-                let revision = if revision.starts_with("0x") {
-                    String::from(&revision[2..])
+                revision = if rev.starts_with("0x") {
+                    String::from(&rev[2..])
                 } else {
-                    String::from(revision)
+                    String::from(rev)
                 };
-                let drv_name = String::from(pdev.driver()
-                    .unwrap().to_str().unwrap());
-
-                let ndinf = DrmDeviceInfo {
-                    pci_dev: sysname.clone(),
-                    vendor_id,
-                    vendor,
-                    device_id,
-                    device,
-                    revision,
-                    drv_name,
-                    ..Default::default()
-                };
-                qmds.infos.insert(sysname.clone(), ndinf);
+                if let Some(ssid) = pdev.property_value("PCI_SUBSYS_ID") {
+                    let ssid = ssid.to_str().unwrap();
+                    subsys_vendor_id = String::from(&ssid[0..4]);
+                    subsys_vendor = self.find_vendor(&subsys_vendor_id);
+                    subsys_device_id = String::from(&ssid[5..9]);
+                    subsys_device = self.find_subsystem(&vendor_id,
+                        &device_id, &subsys_vendor_id, &subsys_device_id);
+                }
+            } else {
+                // platform device (e.g. Arm SoC GPU), keyed by sysname
+                vendor_id = String::new();
+                device_id = String::new();
+                revision = String::new();
+                (vendor, device) = DrmDevices::platform_names(&pdev);
+            }
+
+            let ndinf = DrmDeviceInfo {
+                pci_dev: sysname.clone(),
+                vendor_id,
+                vendor,
+                device_id,
+                device,
+                revision,
+                subsys_vendor_id,
+                subsys_vendor,
+                subsys_device_id,
+                subsys_device,
+                drv_name,
+                ..Default::default()
+            };
+            self.infos.insert(sysname.clone(), ndinf);
+        }
+
+        let devnode = String::from(d.devnode().unwrap().to_str().unwrap());
+        let devnum = d.devnum().unwrap();
+        let minf = DrmMinorInfo::from(&devnode, devnum)?;
+
+        let dinf = self.infos.get_mut(&sysname).unwrap();
+        if dinf.drm_minors.iter().any(|m| m.devnode == minf.devnode) {
+            return Ok(None);
+        }
+        dinf.drm_minors.push(minf);
+        // keep card nodes first, as udev enumeration does
+        dinf.drm_minors.sort_by(|a, b| a.devnode.cmp(&b.devnode));
+
+        Ok(Some(sysname))
+    }
+
+    // display-only platform devices have nothing for us to show
+    fn has_stats(dinf: &DrmDeviceInfo) -> bool
+    {
+        dinf.is_pci() || dinf.drm_minors.iter().any(|m| m.is_accel() ||
+            m.devnode.contains("render"))
+    }
+
+    // DRM driver version along with its out-of-tree module version or,
+    // for in-tree drivers, the running kernel release
+    fn driver_version(dinf: &DrmDeviceInfo) -> String
+    {
+        let mut dn = &dinf.drm_minors[0].devnode;
+        for m in dinf.drm_minors.iter() {
+            if m.devnode.contains("render") {
+                dn = &m.devnode;
+                break;
             }
+        }
 
-            let devnode = String::from(d.devnode().unwrap().to_str().unwrap());
-            let devnum = d.devnum().unwrap();
-            let minf = DrmMinorInfo::from(&devnode, devnum)?;
+        let mut ver = match drm_drivers::drm_version(dn) {
+            Ok(v) => v,
+            Err(err) => {
+                debug!("ERR: failed to get DRM version from {:?}: {:?}",
+                    dn, err);
+                String::from("N/A")
+            }
+        };
+
+        let mpath = Path::new("/sys/module").join(&dinf.drv_name)
+            .join("version");
+        if let Ok(mver) = fs::read_to_string(mpath) {
+            ver.push_str(&format!(" (module {})", mver.trim()));
+        } else if let Ok(kver) = fs::read_to_string(
+            "/proc/sys/kernel/osrelease") {
+            ver.push_str(&format!(" (kernel {})", kver.trim()));
+        }
 
-            let dinf = qmds.infos.get_mut(&sysname).unwrap();
-            dinf.drm_minors.push(minf);
+        ver
+    }
+
+    fn init_driver(dinf: &mut DrmDeviceInfo) -> Result<()>
+    {
+        dinf.drv_version = DrmDevices::driver_version(dinf);
+
+        if let Some(drv_ref) = drm_drivers::driver_from(dinf)? {
+            let dref = drv_ref.clone();
+            let mut drv_b = dref.borrow_mut();
+
+            dinf.dev_type = drv_b.dev_type()?;
+            dinf.freq_limits = drv_b.freq_limits()?;
+            dinf.power_limits = drv_b.power_limits()?;
+            dinf.caps = drv_b.capabilities()?;
+            dinf.firmwares = drv_b.firmwares()?;
+            dinf.driver = Some(drv_ref);
         }
 
-        for dinf in qmds.infos.values_mut() {
-            if let Some(drv_ref) = drm_drivers::driver_from(dinf)? {
-                let dref = drv_ref.clone();
-                let mut drv_b = dref.borrow_mut();
+        Ok(())
+    }
 
-                dinf.dev_type = drv_b.dev_type()?;
-                dinf.freq_limits = drv_b.freq_limits()?;
-                dinf.driver = Some(drv_ref);
+    fn is_drm_node(d: &udev::Device) -> bool
+    {
+        if let Some(dn) = d.devnode() {
+            let dn = dn.to_str().unwrap();
+            return dn.starts_with("/dev/dri/") || dn.starts_with("/dev/accel/");
+        }
+
+        false
+    }
+
+    fn remove_minor(&mut self, devnode: &str)
+    {
+        // DRM minors only go away when the whole device is unregistered
+        // (e.g. hot-unplug or driver unbind), so drop the device and its
+        // driver (closing any open fds on it)
+        self.infos.retain(|sn, di| {
+            let keep = !di.drm_minors.iter().any(|m| m.devnode == devnode);
+            if !keep {
+                debug!("INF: Removing DRM device {:?} ({:?} removed).",
+                    sn, devnode);
             }
+            keep
+        });
+    }
+
+    fn handle_hotplug(&mut self) -> Result<()>
+    {
+        let mon = if let Some(m) = &mut self.monitor {
+            m
+        } else {
+            return Ok(());
+        };
+
+        // devices added but not bound to a driver yet are retried later
+        let mut added = mem::take(&mut mon.pending);
+        let mut removed = Vec::new();
+        for ev in mon.socket.iter() {
+            if !DrmDevices::is_drm_node(&ev) {
+                continue;
+            }
+            let dn = ev.devnode().unwrap().to_str().unwrap().to_string();
+            debug!("INF: udev {} event for {:?}", ev.event_type(), dn);
+
+            match ev.event_type() {
+                udev::EventType::Add => {
+                    added.push(ev.syspath().to_path_buf());
+                },
+                udev::EventType::Remove => {
+                    added.retain(|sp| sp != ev.syspath());
+                    removed.push(dn);
+                },
+                _ => {}
+            }
+        }
+
+        for dn in removed.iter() {
+            self.remove_minor(dn);
+        }
+
+        let mut new_devs = Vec::new();
+        let mut pending = Vec::new();
+        for sp in added {
+            let d = if let Ok(d) = udev::Device::from_syspath(&sp) {
+                d
+            } else {
+                continue;  // already gone
+            };
+            let is_new = if let Some(pdev) = d.parent() {
+                !self.infos.contains_key(pdev.sysname().to_str().unwrap())
+            } else {
+                continue;
+            };
+
+            match self.add_minor(&d) {
+                Ok(Some(sn)) => {
+                    if is_new {
+                        new_devs.push(sn);
+                    }
+                },
+                Ok(None) => {
+                    if is_new {
+                        pending.push(sp);
+                    }
+                },
+                Err(err) => {
+                    debug!("ERR: failed to add DRM minor {:?}: {:?}",
+                        sp, err);
+                },
+            }
+        }
+        self.monitor.as_mut().unwrap().pending = pending;
+
+        for sn in new_devs {
+            let dinf = self.infos.get_mut(&sn).unwrap();
+            if !DrmDevices::has_stats(dinf) {
+                debug!("INF: Ignoring platform device without render node: {:?}",
+                    sn);
+                self.infos.remove(&sn);
+                continue;
+            }
+
+            // same as at startup, keep it even without driver stats
+            if let Err(err) = DrmDevices::init_driver(dinf) {
+                debug!("ERR: failed to init driver for new device {:?}: {:?}",
+                    sn, err);
+            }
+            debug!("INF: Added DRM device {:?}.", sn);
+        }
+
+        Ok(())
+    }
+
+    pub fn find_devices(pci_ids_path: Option<&str>) -> Result<DrmDevices>
+    {
+        let mut qmds = DrmDevices::new();
+        qmds.pci_ids_path = pci_ids_path.map(|p| p.to_string());
+
+        let mut enumerator = udev::Enumerator::new()?;
+        enumerator.match_subsystem("drm")?;
+        enumerator.match_subsystem("accel")?;
+        enumerator.match_property("DEVNAME", "/dev/dri/*")?;
+        enumerator.match_property("DEVNAME", "/dev/accel/*")?;
+
+        for d in enumerator.scan_devices()? {
+            if let Err(err) = qmds.add_minor(&d) {
+                debug!("ERR: failed to add DRM minor {:?}: {:?}",
+                    d.syspath(), err);
+            }
+        }
+
+        qmds.infos.retain(|sn, di| {
+            let keep = DrmDevices::has_stats(di);
+            if !keep {
+                debug!("INF: Ignoring platform device without render node: {:?}",
+                    sn);
+            }
+            keep
+        });
+
+        // a device whose driver fails to init is still listed, just without
+        // driver provided stats
+        for (sn, dinf) in qmds.infos.iter_mut() {
+            if let Err(err) = DrmDevices::init_driver(dinf) {
+                debug!("ERR: failed to init driver for device {:?}: {:?}",
+                    sn, err);
+            }
+        }
+
+        // watch for hot-plugged/unplugged devices (if possible)
+        match DrmDevicesMonitor::new() {
+            Ok(mon) => qmds.monitor = Some(mon),
+            Err(err) => debug!("ERR: failed to create udev monitor: {:?}", err),
         }
 
         Ok(qmds)
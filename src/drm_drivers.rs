@@ -1,13 +1,18 @@
 use core::fmt::Debug;
 use std::collections::HashMap;
+use std::fs::File;
+use std::os::fd::AsRawFd;
 use std::cell::RefCell;
 use std::rc::Rc;
+use std::io;
 
 use anyhow::Result;
 
 use crate::drm_devices::{
-    DrmDeviceType, DrmDeviceFreqLimits, DrmDeviceFreqs,
-    DrmDevicePower, DrmDeviceMemInfo, DrmDeviceInfo
+    DrmDeviceType, DrmDeviceCapabilities, DrmDeviceFreqLimits, DrmDeviceFreqs,
+    DrmDeviceDevfreq, DrmDeviceBlockStats, DrmDevicePower, DrmDevicePowerLimit,
+    DrmDeviceMemInfo, DrmDeviceTemperature, DrmDeviceFan,
+    DrmDeviceVfProvisioning, DrmDeviceFirmware, DrmDeviceInfo
 };
 use crate::drm_fdinfo::DrmMemRegion;
 use crate::drm_clients::DrmClientMemInfo;
@@ -18,8 +23,15 @@ mod xe;
 use xe::DrmDriverXe;
 mod i915;
 use i915::DrmDriveri915;
+mod amdgpu_metrics;
 mod amdgpu;
 use amdgpu::DrmDriverAmdgpu;
+mod nouveau;
+use nouveau::DrmDriverNouveau;
+mod soc;
+use soc::DrmDriverSoc;
+mod accel;
+use accel::DrmDriverAccel;
 
 
 pub trait DrmDriver
@@ -34,6 +46,11 @@ pub trait DrmDriver
         Ok(DrmDeviceType::Unknown)
     }
 
+    fn capabilities(&mut self) -> Result<DrmDeviceCapabilities>
+    {
+        Ok(DrmDeviceCapabilities::new())
+    }
+
     fn freq_limits(&mut self) -> Result<DrmDeviceFreqLimits>
     {
         Ok(DrmDeviceFreqLimits::new())
@@ -44,16 +61,52 @@ pub trait DrmDriver
         Ok(DrmDeviceFreqs::new())
     }
 
+    fn devfreq(&mut self) -> Result<Option<DrmDeviceDevfreq>>
+    {
+        Ok(None)
+    }
+
+    fn block_stats(&mut self) -> Result<Option<DrmDeviceBlockStats>>
+    {
+        Ok(None)
+    }
+
     fn power(&mut self) -> Result<DrmDevicePower>
     {
         Ok(DrmDevicePower::new())
     }
 
+    fn power_limits(&mut self) -> Result<Vec<DrmDevicePowerLimit>>
+    {
+        Ok(Vec::new())
+    }
+
     fn mem_info(&mut self) -> Result<DrmDeviceMemInfo>
     {
         Ok(DrmDeviceMemInfo::new())
     }
 
+    fn temps(&mut self) -> Result<Vec<DrmDeviceTemperature>>
+    {
+        Ok(Vec::new())
+    }
+
+    fn fans(&mut self) -> Result<Vec<DrmDeviceFan>>
+    {
+        Ok(Vec::new())
+    }
+
+    fn firmwares(&mut self) -> Result<Vec<DrmDeviceFirmware>>
+    {
+        Ok(Vec::new())
+    }
+
+    fn vf_provisioning(&mut self,
+        _nr_vfs: u32) -> Result<Vec<DrmDeviceVfProvisioning>>
+    {
+        Ok(Vec::new())
+    }
+
     fn client_mem_info(&mut self,
         _mem_regs: &HashMap<String, DrmMemRegion>) -> Result<DrmClientMemInfo>
     {
@@ -68,6 +121,36 @@ impl Debug for dyn DrmDriver
     }
 }
 
+// returns "major.minor.patchlevel" from the DRM core version ioctl
+pub fn drm_version(devnode: &str) -> Result<String>
+{
+    let file = File::open(devnode)?;
+    let mut name = [0 as libc::c_char; 64];
+    let mut date = [0 as libc::c_char; 64];
+    let mut desc = [0 as libc::c_char; 128];
+
+    let mut ver = helpers::drm_version {
+        version_major: 0,
+        version_minor: 0,
+        version_patchlevel: 0,
+        name_len: name.len(),
+        name: name.as_mut_ptr(),
+        date_len: date.len(),
+        date: date.as_mut_ptr(),
+        desc_len: desc.len(),
+        desc: desc.as_mut_ptr(),
+    };
+
+    let res = unsafe {
+        libc::ioctl(file.as_raw_fd(), helpers::DRM_IOCTL_VERSION, &mut ver) };
+    if res < 0 {
+        return Err(io::Error::last_os_error().into());
+    }
+
+    Ok(format!("{}.{}.{}",
+        ver.version_major, ver.version_minor, ver.version_patchlevel))
+}
+
 pub fn driver_from(
     qmd: &DrmDeviceInfo) -> Result<Option<Rc<RefCell<dyn DrmDriver>>>>
 {
@@ -76,6 +159,16 @@ pub fn driver_from(
         ("xe", DrmDriverXe::new),
         ("i915", DrmDriveri915::new),
         ("amdgpu", DrmDriverAmdgpu::new),
+        ("nouveau", DrmDriverNouveau::new),
+        ("panfrost", DrmDriverSoc::new),
+        ("panthor", DrmDriverSoc::new),
+        ("msm", DrmDriverSoc::new),
+        ("v3d", DrmDriverSoc::new),
+        ("etnaviv", DrmDriverSoc::new),
+        ("lima", DrmDriverSoc::new),
+        ("intel_vpu", DrmDriverAccel::new),
+        ("amdxdna", DrmDriverAccel::new),
+        ("habanalabs", DrmDriverAccel::new),
     ];
 
     for (dn, drv_newfunc) in drvs {
@@ -0,0 +1,98 @@
+use std::collections::HashMap;
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::mem;
+use std::io;
+
+use anyhow::Result;
+use log::warn;
+use libc;
+
+use crate::drm_drivers::DrmDriver;
+use crate::drm_devices::{
+    DrmDeviceType, DrmDeviceCapabilities, DrmDeviceMemInfo, DrmDeviceInfo
+};
+use crate::drm_fdinfo::DrmMemRegion;
+use crate::drm_clients::DrmClientMemInfo;
+
+
+// Compute accelerators (NPUs) exposed through /dev/accel nodes, they report
+// engines and memory through the common DRM fdinfo keys (e.g. drm-engine-npu
+// on intel_vpu), but have no device-wide stats in a common interface.
+#[derive(Debug)]
+pub struct DrmDriverAccel
+{
+    drv_name: String,
+}
+
+impl DrmDriver for DrmDriverAccel
+{
+    fn name(&self) -> &str
+    {
+        &self.drv_name
+    }
+
+    fn dev_type(&mut self) -> Result<DrmDeviceType>
+    {
+        Ok(match self.drv_name.as_str() {
+            "intel_vpu" | "amdxdna" => DrmDeviceType::Integrated,
+            "habanalabs" => DrmDeviceType::Discrete,
+            _ => DrmDeviceType::Unknown,
+        })
+    }
+
+    fn capabilities(&mut self) -> Result<DrmDeviceCapabilities>
+    {
+        Ok(DrmDeviceCapabilities {
+            client_mem_info: true,
+            ..DrmDeviceCapabilities::new()
+        })
+    }
+
+    fn mem_info(&mut self) -> Result<DrmDeviceMemInfo>
+    {
+        let mut si: libc::sysinfo = unsafe { mem::zeroed() };
+        let res = unsafe { libc::sysinfo(&mut si) };
+        if res < 0 {
+            return Err(io::Error::last_os_error().into());
+        }
+
+        Ok(DrmDeviceMemInfo {
+            smem_total: si.totalram as u64 * si.mem_unit as u64,
+            ..DrmDeviceMemInfo::new()
+        })
+    }
+
+    fn client_mem_info(&mut self,
+        mem_regs: &HashMap<String, DrmMemRegion>) -> Result<DrmClientMemInfo>
+    {
+        let mut cmi = DrmClientMemInfo::new();
+
+        for mr in mem_regs.values() {
+            // "memory" is the generic region from drm_show_memory_stats()
+            if mr.name == "memory" || mr.name.starts_with("system") {
+                cmi.smem_used += mr.total;
+                cmi.smem_rss += mr.resident;
+            } else if mr.name.starts_with("vram") {
+                cmi.vram_used += mr.total;
+                cmi.vram_rss += mr.resident;
+            } else {
+                warn!("Unknown {} memory region: {:?}, skpping it.",
+                    self.drv_name, mr.name);
+                continue;
+            }
+        }
+
+        Ok(cmi)
+    }
+}
+
+impl DrmDriverAccel
+{
+    pub fn new(qmd: &DrmDeviceInfo) -> Result<Rc<RefCell<dyn DrmDriver>>>
+    {
+        Ok(Rc::new(RefCell::new(DrmDriverAccel {
+            drv_name: qmd.drv_name.clone(),
+        })))
+    }
+}
@@ -3,11 +3,13 @@
 #![allow(non_upper_case_globals)]
 
 use std::collections::HashMap;
+use std::cmp::{max, min};
 use std::path::{Path, PathBuf};
 use std::fs::{self, File};
 use std::os::fd::{RawFd, AsRawFd};
 use std::cell::RefCell;
 use std::rc::Rc;
+use std::time::{Duration, Instant};
 use std::mem;
 use std::io;
 
@@ -17,10 +19,12 @@ use libc;
 
 use crate::drm_drivers::DrmDriver;
 use crate::drm_drivers::helpers::drm_iow;
+use crate::drm_drivers::amdgpu_metrics::AmdgpuMetrics;
 use crate::hwmon::Hwmon;
 use crate::drm_devices::{
-    DrmDeviceType, DrmDeviceFreqLimits, DrmDeviceFreqs,
-    DrmDevicePower, DrmDeviceMemInfo, DrmDeviceInfo
+    DrmDeviceType, DrmDeviceCapabilities, DrmDeviceFreqLimits, DrmDeviceFreqs,
+    DrmDeviceBlockStats, DrmDevicePower, DrmDevicePowerLimit, DrmDeviceMemInfo,
+    DrmDeviceTemperature, DrmDeviceFan, DrmDeviceFirmware, DrmDeviceInfo
 };
 use crate::drm_fdinfo::DrmMemRegion;
 use crate::drm_clients::DrmClientMemInfo;
@@ -241,6 +245,51 @@ struct drm_amdgpu_query_fw {
    _pad: u32,
 }
 
+const AMDGPU_INFO_FW_VERSION: u32 = 0x0e;
+const AMDGPU_INFO_FW_VCE: u32 = 0x01;
+const AMDGPU_INFO_FW_UVD: u32 = 0x02;
+const AMDGPU_INFO_FW_GMC: u32 = 0x03;
+const AMDGPU_INFO_FW_GFX_ME: u32 = 0x04;
+const AMDGPU_INFO_FW_GFX_PFP: u32 = 0x05;
+const AMDGPU_INFO_FW_GFX_CE: u32 = 0x06;
+const AMDGPU_INFO_FW_GFX_RLC: u32 = 0x07;
+const AMDGPU_INFO_FW_GFX_MEC: u32 = 0x08;
+const AMDGPU_INFO_FW_SMC: u32 = 0x0a;
+const AMDGPU_INFO_FW_SDMA: u32 = 0x0b;
+const AMDGPU_INFO_FW_SOS: u32 = 0x0c;
+const AMDGPU_INFO_FW_ASD: u32 = 0x0d;
+const AMDGPU_INFO_FW_VCN: u32 = 0x0e;
+const AMDGPU_INFO_FW_DMCU: u32 = 0x12;
+const AMDGPU_INFO_FW_TA: u32 = 0x13;
+const AMDGPU_INFO_FW_DMCUB: u32 = 0x14;
+const AMDGPU_INFO_FW_TOC: u32 = 0x15;
+const AMDGPU_INFO_FW_CAP: u32 = 0x16;
+const AMDGPU_INFO_FW_MES_KIQ: u32 = 0x19;
+const AMDGPU_INFO_FW_MES: u32 = 0x1a;
+const AMDGPU_INFO_FW_IMU: u32 = 0x1b;
+const AMDGPU_INFO_FW_VPE: u32 = 0x1c;
+
+const AMDGPU_FW_TYPES: &[(&str, u32)] = &[
+    ("VCE", AMDGPU_INFO_FW_VCE), ("UVD", AMDGPU_INFO_FW_UVD),
+    ("MC", AMDGPU_INFO_FW_GMC), ("ME", AMDGPU_INFO_FW_GFX_ME),
+    ("PFP", AMDGPU_INFO_FW_GFX_PFP), ("CE", AMDGPU_INFO_FW_GFX_CE),
+    ("RLC", AMDGPU_INFO_FW_GFX_RLC), ("MEC", AMDGPU_INFO_FW_GFX_MEC),
+    ("SMC", AMDGPU_INFO_FW_SMC), ("SDMA", AMDGPU_INFO_FW_SDMA),
+    ("SOS", AMDGPU_INFO_FW_SOS), ("ASD", AMDGPU_INFO_FW_ASD),
+    ("VCN", AMDGPU_INFO_FW_VCN), ("DMCU", AMDGPU_INFO_FW_DMCU),
+    ("TA", AMDGPU_INFO_FW_TA), ("DMCUB", AMDGPU_INFO_FW_DMCUB),
+    ("TOC", AMDGPU_INFO_FW_TOC), ("CAP", AMDGPU_INFO_FW_CAP),
+    ("MES KIQ", AMDGPU_INFO_FW_MES_KIQ), ("MES", AMDGPU_INFO_FW_MES),
+    ("IMU", AMDGPU_INFO_FW_IMU), ("VPE", AMDGPU_INFO_FW_VPE),
+];
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+struct drm_amdgpu_info_firmware {
+    ver: u32,
+    feature: u32,
+}
+
 #[repr(C)]
 #[derive(Debug, Copy, Clone)]
 struct drm_amdgpu_info_vbios_info {
@@ -254,6 +303,24 @@ struct drm_amdgpu_info_sensor_info {
     type_: u32,
 }
 
+const AMDGPU_INFO_VBIOS: u32 = 0x1B;
+const AMDGPU_INFO_VBIOS_INFO: u32 = 0x3;
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+struct drm_amdgpu_info_vbios {
+    name: [u8; 64usize],
+    vbios_pn: [u8; 64usize],
+    version: u32,
+    pad: u32,
+    vbios_ver_str: [u8; 32usize],
+    date: [u8; 32usize],
+}
+
+const AMDGPU_INFO_SENSOR: u32 = 0x1D;
+const AMDGPU_INFO_SENSOR_GFX_SCLK: u32 = 0x1;
+const AMDGPU_INFO_SENSOR_GFX_MCLK: u32 = 0x2;
+
 #[repr(C)]
 #[derive(Debug, Copy, Clone)]
 struct drm_amdgpu_info_video_cap {
@@ -301,6 +368,9 @@ impl drm_amdgpu_info
     }
 }
 
+// all driver calls from one device refresh reuse the same gpu_metrics read
+const AMDGPU_METRICS_MAX_AGE: Duration = Duration::from_millis(50);
+
 const DRM_AMDGPU_INFO: u64 = 0x05;
 const DRM_IOCTL_AMDGPU_INFO: u64 = drm_iow!(DRM_AMDGPU_INFO,
     mem::size_of::<drm_amdgpu_info>());
@@ -311,6 +381,9 @@ pub struct DrmDriverAmdgpu
     _dn_file: File,
     dn_fd: RawFd,
     freqs_dir: PathBuf,
+    metrics_path: Option<PathBuf>,
+    metrics: Option<AmdgpuMetrics>,
+    metrics_time: Option<Instant>,
     dev_type: Option<DrmDeviceType>,
     freq_limits: Option<DrmDeviceFreqLimits>,
     hwmon: Option<Hwmon>,
@@ -364,49 +437,74 @@ impl DrmDriver for DrmDriverAmdgpu
         })
     }
 
+    fn capabilities(&mut self) -> Result<DrmDeviceCapabilities>
+    {
+        let has_power = (self.hwmon.is_some() && !self.sensor.is_empty()) ||
+            self.gpu_metrics().is_some_and(|gm| gm.socket_power.is_some() ||
+                gm.gfx_power.is_some());
+
+        Ok(DrmDeviceCapabilities {
+            smem_used: true,
+            vram: true,
+            freqs: true,
+            power: has_power,
+            temps: self.temps().is_ok_and(|t| !t.is_empty()),
+            fans: self.fans().is_ok_and(|f| !f.is_empty()),
+            client_mem_info: true,
+        })
+    }
+
     fn freq_limits(&mut self) -> Result<DrmDeviceFreqLimits>
     {
         if let Some(fls) = &self.freq_limits {
             return Ok(fls.clone());
         }
 
-        let fpath = self.freqs_dir.join("pp_dpm_sclk");
-        let sclk_str = fs::read_to_string(&fpath)?;
-
         let mut fls = DrmDeviceFreqLimits::new();
-        for line in sclk_str.lines() {
-            let kv: Vec<_> = line.split(':').map(|it| it.trim()).collect();
-            if kv.len() < 2 {
-                warn!("Wrong line [{:?}] from {:?}, aborting.", line, fpath);
-                return Ok(fls);
+
+        // engine/memory clocks (in kHz) from device info query
+        let mut qid = drm_amdgpu_info_device::new();
+        let qid_ptr: *mut drm_amdgpu_info_device = &mut qid;
+
+        self.amdgpu_info_ioctl(AMDGPU_INFO_DEV_INFO,
+            qid_ptr as u64, mem::size_of::<drm_amdgpu_info_device>() as u32)?;
+
+        fls.minimum = qid.min_engine_clock / 1000;
+        fls.maximum = qid.max_engine_clock / 1000;
+        fls.mem_minimum = qid.min_memory_clock / 1000;
+        fls.mem_maximum = qid.max_memory_clock / 1000;
+
+        // overdrive range set by the user, if enabled
+        if let Some((mn, mx)) = self.od_clk_range("OD_SCLK") {
+            if fls.minimum == 0 {
+                fls.minimum = mn;
             }
-            let k: u32 = kv[0].parse()?;
-            if k == 1 {
-                continue;
+            if fls.maximum == 0 {
+                fls.maximum = mx;
             }
-
-            let mut v = kv[1];
-            if v.ends_with(" *") {
-                v = &kv[1][..kv[1].len() - 2];
+        }
+        if let Some((mn, mx)) = self.od_clk_range("OD_MCLK") {
+            if fls.mem_minimum == 0 {
+                fls.mem_minimum = mn;
             }
-            if !v.ends_with("Mhz") {
-                warn!("Wrong line [{:?}] from {:?}, aborting.", line, fpath);
-                return Ok(fls);
+            if fls.mem_maximum == 0 {
+                fls.mem_maximum = mx;
             }
-            v = &v[..v.len() - 3];
-
-            if k == 0 {
-                // FIXME: actual freq can go lower then minimum in sysfs file
-                fls.minimum = 0;
-            } else if k == 2 {
-                fls.maximum = v.parse()?;
-                // FIXME: actual freq can go much higher than max in sysfs file
-                fls.maximum += fls.maximum / 2;
-            } else {
-                fls.minimum = 0;
-                fls.maximum = 0;
-                warn!("Wrong line [{:?}] from {:?}, aborting.", line, fpath);
-                return Ok(fls);
+        }
+
+        // DPM levels can go below the minimum (e.g. deep sleep "S" level)
+        if let Ok((lvls, _)) = self.dpm_levels("pp_dpm_sclk") {
+            if let (Some(mn), Some(mx)) = (lvls.iter().min(), lvls.iter().max()) {
+                fls.minimum = if fls.minimum == 0 {
+                    *mn } else { min(fls.minimum, *mn) };
+                fls.maximum = max(fls.maximum, *mx);
+            }
+        }
+        if let Ok((lvls, _)) = self.dpm_levels("pp_dpm_mclk") {
+            if let (Some(mn), Some(mx)) = (lvls.iter().min(), lvls.iter().max()) {
+                fls.mem_minimum = if fls.mem_minimum == 0 {
+                    *mn } else { min(fls.mem_minimum, *mn) };
+                fls.mem_maximum = max(fls.mem_maximum, *mx);
             }
         }
 
@@ -416,29 +514,76 @@ impl DrmDriver for DrmDriverAmdgpu
 
     fn freqs(&mut self) -> Result<DrmDeviceFreqs>
     {
-        let fpath = self.freqs_dir.join("pp_dpm_sclk");
-        let sclk_str = fs::read_to_string(&fpath)?;
-
+        let fls = self.freq_limits()?;
         let mut freqs = DrmDeviceFreqs::new();
-        for line in sclk_str.lines() {
-            let tl = line.trim();
-            if !tl.ends_with("Mhz *") {
-                continue;
-            }
-            let kv: Vec<_> = tl[..tl.len() - 5]
-                .split(':').map(|it| it.trim()).collect();
 
-            freqs.act_freq = kv[1].parse()?;
+        freqs.min_freq = fls.minimum;
+        freqs.max_freq = fls.maximum;
+
+        // requested freqs are the current DPM levels
+        if let Ok((_, Some(cur))) = self.dpm_levels("pp_dpm_sclk") {
+            freqs.cur_freq = cur;
+        }
+
+        // actual freqs from sensors, then SMU metrics or DPM levels
+        let gm = self.gpu_metrics();
+        if let Some(gm) = &gm {
+            freqs.throttle_reasons = gm.throttle_reasons();
+        }
+
+        if let Ok(sclk) = self.amdgpu_sensor_ioctl(AMDGPU_INFO_SENSOR_GFX_SCLK) {
+            freqs.act_freq = sclk as u64;
+        } else if let Some(gfxclk) = gm.as_ref().and_then(|g| g.gfxclk) {
+            freqs.act_freq = gfxclk;
+        } else {
+            freqs.act_freq = freqs.cur_freq;
+        }
+
+        if let Ok(mclk) = self.amdgpu_sensor_ioctl(AMDGPU_INFO_SENSOR_GFX_MCLK) {
+            freqs.mem_freq = mclk as u64;
+        } else if let Some(uclk) = gm.as_ref().and_then(|g| g.uclk) {
+            freqs.mem_freq = uclk;
+        } else if let Ok((_, Some(cur))) = self.dpm_levels("pp_dpm_mclk") {
+            freqs.mem_freq = cur;
         }
 
         Ok(freqs)
     }
 
+    fn block_stats(&mut self) -> Result<Option<DrmDeviceBlockStats>>
+    {
+        let gm = if let Some(gm) = self.gpu_metrics() {
+            gm
+        } else {
+            return Ok(None);
+        };
+
+        let mut bs = DrmDeviceBlockStats::new();
+        bs.gfx_activity = gm.gfx_activity;
+        bs.mem_activity = gm.mem_activity;
+        bs.mm_activity = gm.mm_activity;
+        bs.soc_freq = gm.socclk;
+        bs.vclk_freq = gm.vclk;
+        bs.dclk_freq = gm.dclk;
+
+        Ok(Some(bs))
+    }
+
     fn power(&mut self) -> Result<DrmDevicePower>
     {
         if self.hwmon.is_none() || self.sensor.is_empty() {
-            // TODO: need to add integrated support, only hwmon/discrete now
-            return Ok(DrmDevicePower::new());
+            // no hwmon power (e.g. APUs), try SMU gpu_metrics instead
+            let mut pwr = DrmDevicePower::new();
+            if let Some(gm) = self.gpu_metrics() {
+                if let Some(gfx_pwr) = gm.gfx_power {
+                    pwr.gpu_cur_power = gfx_pwr;
+                    pwr.pkg_cur_power = gm.socket_power.unwrap_or(0.0);
+                } else {
+                    pwr.gpu_cur_power = gm.socket_power.unwrap_or(0.0);
+                }
+            }
+
+            return Ok(pwr);
         }
         let hwmon = self.hwmon.as_ref().unwrap();
 
@@ -446,10 +591,109 @@ impl DrmDriver for DrmDriverAmdgpu
 
         Ok(DrmDevicePower {
             gpu_cur_power: val as f64 / 1000000.0,
-            pkg_cur_power: 0.0,
+            ..DrmDevicePower::new()
         })
     }
 
+    fn power_limits(&mut self) -> Result<Vec<DrmDevicePowerLimit>>
+    {
+        if self.hwmon.is_none() {
+            return Ok(Vec::new());
+        }
+
+        self.hwmon.as_ref().unwrap().power_limits()
+    }
+
+    fn temps(&mut self) -> Result<Vec<DrmDeviceTemperature>>
+    {
+        if let Some(hwmon) = &self.hwmon {
+            let temps = hwmon.temps()?;
+            if !temps.is_empty() {
+                return Ok(temps);
+            }
+        }
+
+        // no hwmon temps, try SMU gpu_metrics instead
+        let mut temps = Vec::new();
+        if let Some(gm) = self.gpu_metrics() {
+            let gm_temps = [
+                ("edge", gm.temp_gfx),
+                ("junction", gm.temp_hotspot),
+                ("mem", gm.temp_mem),
+                ("soc", gm.temp_soc),
+            ];
+            for (name, val) in gm_temps {
+                if let Some(tv) = val {
+                    let mut temp = DrmDeviceTemperature::new();
+                    temp.name = name.to_string();
+                    temp.temp = tv;
+                    temps.push(temp);
+                }
+            }
+        }
+
+        Ok(temps)
+    }
+
+    fn fans(&mut self) -> Result<Vec<DrmDeviceFan>>
+    {
+        // APUs rely on the platform fans, nothing to report here
+        if !self.dev_type.as_ref().is_some_and(|dt| dt.is_discrete()) {
+            return Ok(Vec::new());
+        }
+
+        if let Some(hwmon) = &self.hwmon {
+            let fans = hwmon.fans()?;
+            if !fans.is_empty() {
+                return Ok(fans);
+            }
+        }
+
+        // no hwmon fans, try SMU gpu_metrics instead
+        let mut fans = Vec::new();
+        if let Some(gm) = self.gpu_metrics() {
+            if let Some(speed) = gm.fan_speed {
+                let mut fan = DrmDeviceFan::new();
+                fan.name = String::from("fan1");
+                fan.speed = speed;
+                if let Some(pwm) = gm.fan_pwm {
+                    fan.duty = pwm as f64 * 100.0 / 255.0;
+                }
+                fans.push(fan);
+            }
+        }
+
+        Ok(fans)
+    }
+
+    fn firmwares(&mut self) -> Result<Vec<DrmDeviceFirmware>>
+    {
+        let mut fws = Vec::new();
+
+        for (name, fw_type) in AMDGPU_FW_TYPES.iter() {
+            // firmwares not used by the device fail or report version 0
+            if let Ok(fw) = self.amdgpu_fw_ioctl(*fw_type) {
+                if fw.ver > 0 {
+                    fws.push(DrmDeviceFirmware::from(name,
+                        &format!("0x{:08x} (feature {})", fw.ver, fw.feature)));
+                }
+            }
+        }
+
+        if let Ok(vb) = self.amdgpu_vbios_ioctl() {
+            let cstr = |b: &[u8]| String::from_utf8_lossy(
+                b.split(|c| *c == 0).next().unwrap()).trim().to_string();
+            fws.push(DrmDeviceFirmware::from("VBIOS",
+                &format!("{} {} ({}, {})", cstr(&vb.name), cstr(&vb.vbios_pn),
+                    cstr(&vb.vbios_ver_str), cstr(&vb.date))));
+        } else if let Ok(vbv) = fs::read_to_string(
+            self.freqs_dir.join("vbios_version")) {
+            fws.push(DrmDeviceFirmware::from("VBIOS", vbv.trim()));
+        }
+
+        Ok(fws)
+    }
+
     fn client_mem_info(&mut self,
         mem_regs: &HashMap<String, DrmMemRegion>) -> Result<DrmClientMemInfo>
     {
@@ -475,6 +719,93 @@ impl DrmDriver for DrmDriverAmdgpu
 
 impl DrmDriverAmdgpu
 {
+    fn parse_mhz(val: &str) -> Option<u64>
+    {
+        let v = val.trim().trim_end_matches('*').trim_end();
+        let v = v.strip_suffix("Mhz").or_else(|| v.strip_suffix("MHz"))?;
+
+        v.trim().parse().ok()
+    }
+
+    // returns all levels in a pp_dpm_* file and the current one (if any)
+    fn dpm_levels(&self, fname: &str) -> Result<(Vec<u64>, Option<u64>)>
+    {
+        let fpath = self.freqs_dir.join(fname);
+        let dpm_str = fs::read_to_string(&fpath)?;
+
+        let mut lvls = Vec::new();
+        let mut cur = None;
+        for line in dpm_str.lines() {
+            let kv: Vec<_> = line.splitn(2, ':').map(|it| it.trim()).collect();
+            if kv.len() < 2 {
+                debug!("INF: discarding line [{:?}] from {:?}", line, fpath);
+                continue;
+            }
+
+            if let Some(fq) = DrmDriverAmdgpu::parse_mhz(kv[1]) {
+                if kv[1].ends_with('*') {
+                    cur = Some(fq);
+                }
+                lvls.push(fq);
+            } else {
+                debug!("INF: discarding line [{:?}] from {:?}", line, fpath);
+            }
+        }
+
+        Ok((lvls, cur))
+    }
+
+    // returns (min, max) for a section (e.g. OD_SCLK) in pp_od_clk_voltage
+    fn od_clk_range(&self, od_sect: &str) -> Option<(u64, u64)>
+    {
+        let fpath = self.freqs_dir.join("pp_od_clk_voltage");
+        let od_str = fs::read_to_string(&fpath).ok()?;
+
+        let mut fqs: Vec<u64> = Vec::new();
+        let mut in_sect = false;
+        for line in od_str.lines() {
+            let tl = line.trim();
+            if let Some(sect) = tl.strip_suffix(':') {
+                in_sect = sect == od_sect;
+                continue;
+            }
+            if !in_sect {
+                continue;
+            }
+
+            let kv: Vec<_> = tl.splitn(2, ':').map(|it| it.trim()).collect();
+            if kv.len() < 2 {
+                continue;
+            }
+            if let Some(fq) = DrmDriverAmdgpu::parse_mhz(kv[1]) {
+                fqs.push(fq);
+            }
+        }
+
+        Some((*fqs.iter().min()?, *fqs.iter().max()?))
+    }
+
+    fn gpu_metrics(&mut self) -> Option<AmdgpuMetrics>
+    {
+        let mpath = self.metrics_path.as_ref()?;
+
+        if self.metrics_time.is_some_and(
+            |t| t.elapsed() < AMDGPU_METRICS_MAX_AGE) {
+            return self.metrics.clone();
+        }
+
+        self.metrics = match AmdgpuMetrics::from(mpath) {
+            Ok(gm) => gm,
+            Err(err) => {
+                debug!("ERR: failed to read {:?}: {:?}", mpath, err);
+                None
+            }
+        };
+        self.metrics_time = Some(Instant::now());
+
+        self.metrics.clone()
+    }
+
     fn amdgpu_info_ioctl(&self,
         query_id: u32, data: u64, size: u32) -> Result<()>
     {
@@ -493,6 +824,76 @@ impl DrmDriverAmdgpu
         Ok(())
     }
 
+    fn amdgpu_fw_ioctl(&self, fw_type: u32) -> Result<drm_amdgpu_info_firmware>
+    {
+        let mut fw = drm_amdgpu_info_firmware { ver: 0, feature: 0 };
+        let fw_ptr: *mut drm_amdgpu_info_firmware = &mut fw;
+        let mut qi = drm_amdgpu_info::new();
+
+        qi.query = AMDGPU_INFO_FW_VERSION;
+        qi.return_pointer = fw_ptr as u64;
+        qi.return_size = mem::size_of::<drm_amdgpu_info_firmware>() as u32;
+        qi.extra.query_fw = drm_amdgpu_query_fw {
+            fw_type,
+            ip_instance: 0,
+            index: 0,
+            _pad: 0,
+        };
+
+        let res = unsafe {
+            libc::ioctl(self.dn_fd, DRM_IOCTL_AMDGPU_INFO, &mut qi) };
+        if res < 0 {
+            return Err(io::Error::last_os_error().into());
+        }
+
+        Ok(fw)
+    }
+
+    fn amdgpu_vbios_ioctl(&self) -> Result<drm_amdgpu_info_vbios>
+    {
+        let mut vb: drm_amdgpu_info_vbios = unsafe { mem::zeroed() };
+        let vb_ptr: *mut drm_amdgpu_info_vbios = &mut vb;
+        let mut qi = drm_amdgpu_info::new();
+
+        qi.query = AMDGPU_INFO_VBIOS;
+        qi.return_pointer = vb_ptr as u64;
+        qi.return_size = mem::size_of::<drm_amdgpu_info_vbios>() as u32;
+        qi.extra.vbios_info = drm_amdgpu_info_vbios_info {
+            type_: AMDGPU_INFO_VBIOS_INFO,
+            offset: 0,
+        };
+
+        let res = unsafe {
+            libc::ioctl(self.dn_fd, DRM_IOCTL_AMDGPU_INFO, &mut qi) };
+        if res < 0 {
+            return Err(io::Error::last_os_error().into());
+        }
+
+        Ok(vb)
+    }
+
+    fn amdgpu_sensor_ioctl(&self, sensor_type: u32) -> Result<u32>
+    {
+        let mut val: u32 = 0;
+        let val_ptr: *mut u32 = &mut val;
+        let mut qi = drm_amdgpu_info::new();
+
+        qi.query = AMDGPU_INFO_SENSOR;
+        qi.return_pointer = val_ptr as u64;
+        qi.return_size = mem::size_of::<u32>() as u32;
+        qi.extra.sensor_info = drm_amdgpu_info_sensor_info {
+            type_: sensor_type,
+        };
+
+        let res = unsafe {
+            libc::ioctl(self.dn_fd, DRM_IOCTL_AMDGPU_INFO, &mut qi) };
+        if res < 0 {
+            return Err(io::Error::last_os_error().into());
+        }
+
+        Ok(val)
+    }
+
     pub fn new(qmd: &DrmDeviceInfo) -> Result<Rc<RefCell<dyn DrmDriver>>>
     {
         let mut dn: &str = "";
@@ -514,6 +915,9 @@ impl DrmDriverAmdgpu
             _dn_file: file,
             dn_fd: fd,
             freqs_dir: Path::new(&cpath).join("device"),
+            metrics_path: None,
+            metrics: None,
+            metrics_time: None,
             dev_type: None,
             freq_limits: None,
             hwmon: None,
@@ -523,30 +927,35 @@ impl DrmDriverAmdgpu
         amdgpu.dev_type()?;
         amdgpu.freq_limits()?;
 
-        if amdgpu.dev_type.is_some() &&
-            amdgpu.dev_type.as_ref().unwrap().is_discrete() {
-            let hwmon_path = fs::read_dir(
-                Path::new(&cpath).join("device/hwmon"))?
-                .into_iter()
-                .filter(|r| r.is_ok())
-                .map(|r| r.unwrap().path())
-                .find(|r| r.file_name().unwrap()
-                .to_str().unwrap().starts_with("hwmon"));
-
-            if let Some(hmp) = hwmon_path {
-                let hm_opt = Hwmon::from(hmp.to_path_buf())?;
-                if let Some(hwmon) = hm_opt {
-                    let plist = hwmon.sensors("power");
-                    for s in plist.iter() {
-                        if s.has_item("average") {
-                            amdgpu.sensor = s.sensor.clone();
-                        }
+        let mpath = amdgpu.freqs_dir.join("gpu_metrics");
+        if mpath.exists() {
+            match AmdgpuMetrics::from(&mpath) {
+                Ok(Some(gm)) => {
+                    debug!("INF: using {:?} (v{}.{}).", mpath,
+                        gm.format_revision, gm.content_revision);
+                    amdgpu.metrics_path = Some(mpath);
+                },
+                Ok(None) => debug!(
+                    "INF: no usable {:?}/device/gpu_metrics file.", cpath),
+                Err(err) => debug!(
+                    "ERR: failed to read {:?}, using sysfs only: {:?}",
+                    mpath, err),
+            }
+        } else {
+            debug!("INF: no usable {:?}/device/gpu_metrics file.", cpath);
+        }
+
+        if let Some(hwmon) = Hwmon::from_dev_dir(&amdgpu.freqs_dir)? {
+            // only dGPUs have the GPU power alone in hwmon
+            if amdgpu.dev_type.as_ref().is_some_and(|dt| dt.is_discrete()) {
+                let plist = hwmon.sensors("power");
+                for s in plist.iter() {
+                    if s.has_item("average") {
+                        amdgpu.sensor = s.sensor.clone();
                     }
-                    amdgpu.hwmon = Some(hwmon);
                 }
-            } else {
-                debug!("INF: no {:?}/device/hwmon/hwmon* directory.", cpath);
             }
+            amdgpu.hwmon = Some(hwmon);
         }
 
         Ok(Rc::new(RefCell::new(amdgpu)))
@@ -0,0 +1,547 @@
+#![allow(non_camel_case_types)]
+
+use std::path::Path;
+use std::cmp::min;
+use std::fs;
+use std::mem;
+use std::ptr;
+
+use anyhow::Result;
+use log::debug;
+
+use crate::drm_devices::DrmDeviceThrottleReasons;
+
+
+// from Linux kernel v6.12 drivers/gpu/drm/amd/include/kgd_pp_interface.h
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+struct metrics_table_header {
+    structure_size: u16,
+    format_revision: u8,
+    content_revision: u8,
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+struct gpu_metrics_v1_0 {
+    common_header: metrics_table_header,
+    system_clock_counter: u64,
+    temperature_edge: u16,
+    temperature_hotspot: u16,
+    temperature_mem: u16,
+    temperature_vrgfx: u16,
+    temperature_vrsoc: u16,
+    temperature_vrmem: u16,
+    average_gfx_activity: u16,
+    average_umc_activity: u16,
+    average_mm_activity: u16,
+    average_socket_power: u16,
+    energy_accumulator: u32,
+    average_gfxclk_frequency: u16,
+    average_socclk_frequency: u16,
+    average_uclk_frequency: u16,
+    average_vclk0_frequency: u16,
+    average_dclk0_frequency: u16,
+    average_vclk1_frequency: u16,
+    average_dclk1_frequency: u16,
+    current_gfxclk: u16,
+    current_socclk: u16,
+    current_uclk: u16,
+    current_vclk0: u16,
+    current_dclk0: u16,
+    current_vclk1: u16,
+    current_dclk1: u16,
+    throttle_status: u32,
+    current_fan_speed: u16,
+    pcie_link_width: u8,
+    pcie_link_speed: u8,
+}
+
+// v1.1 to v1.3 only append fields, so one layout parses all of them
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+struct gpu_metrics_v1_3 {
+    common_header: metrics_table_header,
+    temperature_edge: u16,
+    temperature_hotspot: u16,
+    temperature_mem: u16,
+    temperature_vrgfx: u16,
+    temperature_vrsoc: u16,
+    temperature_vrmem: u16,
+    average_gfx_activity: u16,
+    average_umc_activity: u16,
+    average_mm_activity: u16,
+    average_socket_power: u16,
+    energy_accumulator: u64,
+    system_clock_counter: u64,
+    average_gfxclk_frequency: u16,
+    average_socclk_frequency: u16,
+    average_uclk_frequency: u16,
+    average_vclk0_frequency: u16,
+    average_dclk0_frequency: u16,
+    average_vclk1_frequency: u16,
+    average_dclk1_frequency: u16,
+    current_gfxclk: u16,
+    current_socclk: u16,
+    current_uclk: u16,
+    current_vclk0: u16,
+    current_dclk0: u16,
+    current_vclk1: u16,
+    current_dclk1: u16,
+    throttle_status: u32,
+    current_fan_speed: u16,
+    pcie_link_width: u16,
+    pcie_link_speed: u16,
+    padding: u16,
+    gfx_activity_acc: u32,
+    mem_activity_acc: u32,
+    temperature_hbm: [u16; 4usize],
+    // v1.2
+    firmware_timestamp: u64,
+    // v1.3
+    voltage_soc: u16,
+    voltage_gfx: u16,
+    voltage_mem: u16,
+    padding1: u16,
+    indep_throttle_status: u64,
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+struct gpu_metrics_v2_0 {
+    common_header: metrics_table_header,
+    system_clock_counter: u64,
+    temperature_gfx: u16,
+    temperature_soc: u16,
+    temperature_core: [u16; 8usize],
+    temperature_l3: [u16; 2usize],
+    average_gfx_activity: u16,
+    average_mm_activity: u16,
+    average_socket_power: u16,
+    average_cpu_power: u16,
+    average_soc_power: u16,
+    average_gfx_power: u16,
+    average_core_power: [u16; 8usize],
+    average_gfxclk_frequency: u16,
+    average_socclk_frequency: u16,
+    average_uclk_frequency: u16,
+    average_fclk_frequency: u16,
+    average_vclk_frequency: u16,
+    average_dclk_frequency: u16,
+    current_gfxclk: u16,
+    current_socclk: u16,
+    current_uclk: u16,
+    current_fclk: u16,
+    current_vclk: u16,
+    current_dclk: u16,
+    current_coreclk: [u16; 8usize],
+    current_l3clk: [u16; 2usize],
+    throttle_status: u32,
+    fan_pwm: u16,
+    padding: u16,
+}
+
+// v2.1 to v2.4 only append fields, so one layout parses all of them
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+struct gpu_metrics_v2_4 {
+    common_header: metrics_table_header,
+    temperature_gfx: u16,
+    temperature_soc: u16,
+    temperature_core: [u16; 8usize],
+    temperature_l3: [u16; 2usize],
+    average_gfx_activity: u16,
+    average_mm_activity: u16,
+    system_clock_counter: u64,
+    average_socket_power: u16,
+    average_cpu_power: u16,
+    average_soc_power: u16,
+    average_gfx_power: u16,
+    average_core_power: [u16; 8usize],
+    average_gfxclk_frequency: u16,
+    average_socclk_frequency: u16,
+    average_uclk_frequency: u16,
+    average_fclk_frequency: u16,
+    average_vclk_frequency: u16,
+    average_dclk_frequency: u16,
+    current_gfxclk: u16,
+    current_socclk: u16,
+    current_uclk: u16,
+    current_fclk: u16,
+    current_vclk: u16,
+    current_dclk: u16,
+    current_coreclk: [u16; 8usize],
+    current_l3clk: [u16; 2usize],
+    throttle_status: u32,
+    fan_pwm: u16,
+    padding: [u16; 3usize],
+    // v2.2
+    indep_throttle_status: u64,
+    // v2.3
+    average_temperature_gfx: u16,
+    average_temperature_soc: u16,
+    average_temperature_core: [u16; 8usize],
+    average_temperature_l3: [u16; 2usize],
+    // v2.4
+    average_cpu_voltage: u16,
+    average_soc_voltage: u16,
+    average_gfx_voltage: u16,
+    average_cpu_current: u16,
+    average_soc_current: u16,
+    average_gfx_current: u16,
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+struct gpu_metrics_v3_0 {
+    common_header: metrics_table_header,
+    temperature_gfx: u16,
+    temperature_soc: u16,
+    temperature_core: [u16; 16usize],
+    temperature_skin: u16,
+    average_gfx_activity: u16,
+    average_vcn_activity: u16,
+    average_ipu_activity: [u16; 8usize],
+    average_core_c0_activity: [u16; 16usize],
+    average_dram_reads: u16,
+    average_dram_writes: u16,
+    average_ipu_reads: u16,
+    average_ipu_writes: u16,
+    system_clock_counter: u64,
+    average_socket_power: u32,
+    average_ipu_power: u16,
+    average_apu_power: u32,
+    average_gfx_power: u32,
+    average_dgpu_power: u32,
+    average_all_core_power: u32,
+    average_core_power: [u16; 16usize],
+    average_sys_power: u16,
+    stapm_power_limit: u16,
+    current_stapm_power_limit: u16,
+    average_gfxclk_frequency: u16,
+    average_socclk_frequency: u16,
+    average_vpeclk_frequency: u16,
+    average_ipuclk_frequency: u16,
+    average_fclk_frequency: u16,
+    average_vclk_frequency: u16,
+    average_uclk_frequency: u16,
+    average_mpipu_frequency: u16,
+    current_coreclk: [u16; 16usize],
+    current_core_maxfreq: u16,
+    current_gfx_maxfreq: u16,
+    throttle_residency_prochot: u32,
+    throttle_residency_spl: u32,
+    throttle_residency_fppt: u32,
+    throttle_residency_sppt: u32,
+    throttle_residency_thm_core: u32,
+    throttle_residency_thm_gfx: u32,
+    throttle_residency_thm_soc: u32,
+    time_filter_alphavalue: u32,
+}
+
+// ASIC independent throttler bits (from amdgpu_smu.h)
+const SMU_THROTTLER_PPT0_BIT: u64 = 0;
+const SMU_THROTTLER_PPT1_BIT: u64 = 1;
+const SMU_THROTTLER_PPT2_BIT: u64 = 2;
+const SMU_THROTTLER_PPT3_BIT: u64 = 3;
+const SMU_THROTTLER_SPL_BIT: u64 = 4;
+const SMU_THROTTLER_FPPT_BIT: u64 = 5;
+const SMU_THROTTLER_SPPT_BIT: u64 = 6;
+const SMU_THROTTLER_SPPT_APU_BIT: u64 = 7;
+const SMU_THROTTLER_TDC_GFX_BIT: u64 = 16;  // 16-20 are all TDC bits
+const SMU_THROTTLER_TDC_CVIP_BIT: u64 = 20;
+const SMU_THROTTLER_EDC_CPU_BIT: u64 = 21;
+const SMU_THROTTLER_EDC_GFX_BIT: u64 = 22;
+const SMU_THROTTLER_APCC_BIT: u64 = 23;
+const SMU_THROTTLER_TEMP_GPU_BIT: u64 = 32;
+const SMU_THROTTLER_TEMP_LIQUID1_BIT: u64 = 43;
+const SMU_THROTTLER_VRHOT0_BIT: u64 = 44;
+const SMU_THROTTLER_VRHOT1_BIT: u64 = 45;
+const SMU_THROTTLER_PROCHOT_CPU_BIT: u64 = 46;
+const SMU_THROTTLER_PROCHOT_GFX_BIT: u64 = 47;
+const SMU_THROTTLER_PPM_BIT: u64 = 56;
+const SMU_THROTTLER_FIT_BIT: u64 = 57;
+
+// fields not supported by a given ASIC are set to all 1s
+const METRICS_U16_INVALID: u16 = 0xFFFF;
+const METRICS_U32_INVALID: u32 = 0xFFFFFFFF;
+
+fn from_u16(val: u16) -> Option<u64>
+{
+    if val == METRICS_U16_INVALID {
+        return None;
+    }
+
+    Some(val as u64)
+}
+
+fn from_u32(val: u32) -> Option<u64>
+{
+    if val == METRICS_U32_INVALID {
+        return None;
+    }
+
+    Some(val as u64)
+}
+
+fn scaled(val: Option<u64>, div: f64) -> Option<f64>
+{
+    val.map(|v| v as f64 / div)
+}
+
+// copies up to size_of::<T>() bytes into a zeroed T, so older content
+// revisions of a layout just leave the newer (appended) fields as 0
+fn layout_from_bytes<T: Copy>(buf: &[u8]) -> T
+{
+    let mut res: T = unsafe { mem::zeroed() };
+    let len = min(buf.len(), mem::size_of::<T>());
+
+    unsafe {
+        ptr::copy_nonoverlapping(buf.as_ptr(),
+            &mut res as *mut T as *mut u8, len);
+    }
+
+    res
+}
+
+#[derive(Debug, Clone)]
+pub struct AmdgpuMetrics
+{
+    pub format_revision: u8,
+    pub content_revision: u8,
+    pub temp_gfx: Option<f64>,          // edge on dGPUs, gfx on APUs (C)
+    pub temp_hotspot: Option<f64>,      // C
+    pub temp_mem: Option<f64>,          // C
+    pub temp_soc: Option<f64>,          // C
+    pub gfx_activity: Option<f64>,      // %
+    pub mem_activity: Option<f64>,      // %
+    pub mm_activity: Option<f64>,       // %
+    pub socket_power: Option<f64>,      // W
+    pub gfx_power: Option<f64>,         // W
+    pub gfxclk: Option<u64>,            // MHz
+    pub socclk: Option<u64>,            // MHz
+    pub uclk: Option<u64>,              // MHz
+    pub vclk: Option<u64>,              // MHz
+    pub dclk: Option<u64>,              // MHz
+    pub fan_speed: Option<u64>,         // RPM
+    pub fan_pwm: Option<u64>,           // PWM (0-255)
+    pub throttle_status: u32,           // ASIC dependent
+    pub indep_throttle_status: Option<u64>,
+}
+
+impl AmdgpuMetrics
+{
+    fn new(frev: u8, crev: u8) -> AmdgpuMetrics
+    {
+        AmdgpuMetrics {
+            format_revision: frev,
+            content_revision: crev,
+            temp_gfx: None,
+            temp_hotspot: None,
+            temp_mem: None,
+            temp_soc: None,
+            gfx_activity: None,
+            mem_activity: None,
+            mm_activity: None,
+            socket_power: None,
+            gfx_power: None,
+            gfxclk: None,
+            socclk: None,
+            uclk: None,
+            vclk: None,
+            dclk: None,
+            fan_speed: None,
+            fan_pwm: None,
+            throttle_status: 0,
+            indep_throttle_status: None,
+        }
+    }
+
+    pub fn throttle_reasons(&self) -> DrmDeviceThrottleReasons
+    {
+        let mut tr = DrmDeviceThrottleReasons::new();
+
+        let ts = match self.indep_throttle_status {
+            Some(ts) => ts,
+            None => {
+                // only know there's throttling, not the reason
+                tr.status = self.throttle_status != 0;
+                return tr;
+            }
+        };
+        let bit = |b: u64| ts & (1 << b) != 0;
+
+        tr.pl1 = bit(SMU_THROTTLER_PPT0_BIT) || bit(SMU_THROTTLER_SPL_BIT);
+        tr.pl2 = bit(SMU_THROTTLER_PPT1_BIT) || bit(SMU_THROTTLER_FPPT_BIT);
+        tr.pl4 = bit(SMU_THROTTLER_PPT2_BIT) ||
+            bit(SMU_THROTTLER_PPT3_BIT) ||
+            bit(SMU_THROTTLER_SPPT_BIT) ||
+            bit(SMU_THROTTLER_SPPT_APU_BIT);
+        tr.prochot = bit(SMU_THROTTLER_PROCHOT_CPU_BIT) ||
+            bit(SMU_THROTTLER_PROCHOT_GFX_BIT);
+        tr.ratl = bit(SMU_THROTTLER_EDC_CPU_BIT) ||
+            bit(SMU_THROTTLER_EDC_GFX_BIT) ||
+            bit(SMU_THROTTLER_APCC_BIT) ||
+            bit(SMU_THROTTLER_PPM_BIT) ||
+            bit(SMU_THROTTLER_FIT_BIT);
+        tr.thermal = (SMU_THROTTLER_TEMP_GPU_BIT..=
+            SMU_THROTTLER_TEMP_LIQUID1_BIT).any(bit);
+        tr.vr_tdc = (SMU_THROTTLER_TDC_GFX_BIT..=
+            SMU_THROTTLER_TDC_CVIP_BIT).any(bit);
+        tr.vr_thermalert = bit(SMU_THROTTLER_VRHOT0_BIT) ||
+            bit(SMU_THROTTLER_VRHOT1_BIT);
+        tr.status = ts != 0;
+
+        tr
+    }
+
+    fn from_v1_0(buf: &[u8]) -> AmdgpuMetrics
+    {
+        let gm: gpu_metrics_v1_0 = layout_from_bytes(buf);
+        let mut res = AmdgpuMetrics::new(1, 0);
+
+        res.temp_gfx = scaled(from_u16(gm.temperature_edge), 1.0);
+        res.temp_hotspot = scaled(from_u16(gm.temperature_hotspot), 1.0);
+        res.temp_mem = scaled(from_u16(gm.temperature_mem), 1.0);
+        res.gfx_activity = scaled(from_u16(gm.average_gfx_activity), 1.0);
+        res.mem_activity = scaled(from_u16(gm.average_umc_activity), 1.0);
+        res.mm_activity = scaled(from_u16(gm.average_mm_activity), 1.0);
+        res.socket_power = scaled(from_u16(gm.average_socket_power), 1.0);
+        res.gfxclk = from_u16(gm.current_gfxclk);
+        res.socclk = from_u16(gm.current_socclk);
+        res.uclk = from_u16(gm.current_uclk);
+        res.vclk = from_u16(gm.current_vclk0);
+        res.dclk = from_u16(gm.current_dclk0);
+        res.fan_speed = from_u16(gm.current_fan_speed);
+        res.throttle_status = gm.throttle_status;
+
+        res
+    }
+
+    fn from_v1_x(crev: u8, buf: &[u8]) -> AmdgpuMetrics
+    {
+        let gm: gpu_metrics_v1_3 = layout_from_bytes(buf);
+        let mut res = AmdgpuMetrics::new(1, crev);
+
+        res.temp_gfx = scaled(from_u16(gm.temperature_edge), 1.0);
+        res.temp_hotspot = scaled(from_u16(gm.temperature_hotspot), 1.0);
+        res.temp_mem = scaled(from_u16(gm.temperature_mem), 1.0);
+        res.gfx_activity = scaled(from_u16(gm.average_gfx_activity), 1.0);
+        res.mem_activity = scaled(from_u16(gm.average_umc_activity), 1.0);
+        res.mm_activity = scaled(from_u16(gm.average_mm_activity), 1.0);
+        res.socket_power = scaled(from_u16(gm.average_socket_power), 1.0);
+        res.gfxclk = from_u16(gm.current_gfxclk);
+        res.socclk = from_u16(gm.current_socclk);
+        res.uclk = from_u16(gm.current_uclk);
+        res.vclk = from_u16(gm.current_vclk0);
+        res.dclk = from_u16(gm.current_dclk0);
+        res.fan_speed = from_u16(gm.current_fan_speed);
+        res.throttle_status = gm.throttle_status;
+        if crev >= 3 {
+            res.indep_throttle_status = Some(gm.indep_throttle_status);
+        }
+
+        res
+    }
+
+    fn from_v2_0(buf: &[u8]) -> AmdgpuMetrics
+    {
+        let gm: gpu_metrics_v2_0 = layout_from_bytes(buf);
+        let mut res = AmdgpuMetrics::new(2, 0);
+
+        res.temp_gfx = scaled(from_u16(gm.temperature_gfx), 100.0);
+        res.temp_soc = scaled(from_u16(gm.temperature_soc), 100.0);
+        res.gfx_activity = scaled(from_u16(gm.average_gfx_activity), 1.0);
+        res.mm_activity = scaled(from_u16(gm.average_mm_activity), 1.0);
+        res.socket_power = scaled(from_u16(gm.average_socket_power), 1000.0);
+        res.gfx_power = scaled(from_u16(gm.average_gfx_power), 1000.0);
+        res.gfxclk = from_u16(gm.current_gfxclk);
+        res.socclk = from_u16(gm.current_socclk);
+        res.uclk = from_u16(gm.current_uclk);
+        res.vclk = from_u16(gm.current_vclk);
+        res.dclk = from_u16(gm.current_dclk);
+        res.fan_pwm = from_u16(gm.fan_pwm);
+        res.throttle_status = gm.throttle_status;
+
+        res
+    }
+
+    fn from_v2_x(crev: u8, buf: &[u8]) -> AmdgpuMetrics
+    {
+        let gm: gpu_metrics_v2_4 = layout_from_bytes(buf);
+        let mut res = AmdgpuMetrics::new(2, crev);
+
+        res.temp_gfx = scaled(from_u16(gm.temperature_gfx), 100.0);
+        res.temp_soc = scaled(from_u16(gm.temperature_soc), 100.0);
+        // activity is in centi-percent from v2.4 on
+        let act_scale = if crev >= 4 { 100.0 } else { 1.0 };
+        res.gfx_activity = scaled(from_u16(gm.average_gfx_activity), act_scale);
+        res.mm_activity = scaled(from_u16(gm.average_mm_activity), act_scale);
+        res.socket_power = scaled(from_u16(gm.average_socket_power), 1000.0);
+        res.gfx_power = scaled(from_u16(gm.average_gfx_power), 1000.0);
+        res.gfxclk = from_u16(gm.current_gfxclk);
+        res.socclk = from_u16(gm.current_socclk);
+        res.uclk = from_u16(gm.current_uclk);
+        res.vclk = from_u16(gm.current_vclk);
+        res.dclk = from_u16(gm.current_dclk);
+        res.fan_pwm = from_u16(gm.fan_pwm);
+        res.throttle_status = gm.throttle_status;
+        if crev >= 2 {
+            res.indep_throttle_status = Some(gm.indep_throttle_status);
+        }
+
+        res
+    }
+
+    fn from_v3_0(buf: &[u8]) -> AmdgpuMetrics
+    {
+        let gm: gpu_metrics_v3_0 = layout_from_bytes(buf);
+        let mut res = AmdgpuMetrics::new(3, 0);
+
+        // no current clocks for gfx & soc, use time filtered ones
+        res.temp_gfx = scaled(from_u16(gm.temperature_gfx), 100.0);
+        res.temp_soc = scaled(from_u16(gm.temperature_soc), 100.0);
+        res.gfx_activity = scaled(from_u16(gm.average_gfx_activity), 100.0);
+        res.mm_activity = scaled(from_u16(gm.average_vcn_activity), 100.0);
+        res.socket_power = scaled(from_u32(gm.average_socket_power), 1000.0);
+        res.gfx_power = scaled(from_u32(gm.average_gfx_power), 1000.0);
+        res.gfxclk = from_u16(gm.average_gfxclk_frequency);
+        res.socclk = from_u16(gm.average_socclk_frequency);
+        res.uclk = from_u16(gm.average_uclk_frequency);
+        res.vclk = from_u16(gm.average_vclk_frequency);
+
+        res
+    }
+
+    pub fn from_bytes(buf: &[u8]) -> Option<AmdgpuMetrics>
+    {
+        if buf.len() < mem::size_of::<metrics_table_header>() {
+            debug!("ERR: gpu_metrics too short ({:?} bytes).", buf.len());
+            return None;
+        }
+        let hdr: metrics_table_header = layout_from_bytes(buf);
+
+        let sz = min(buf.len(), hdr.structure_size as usize);
+        let buf = &buf[..sz];
+
+        match (hdr.format_revision, hdr.content_revision) {
+            (1, 0) => Some(AmdgpuMetrics::from_v1_0(buf)),
+            (1, crev) if crev <= 3 => Some(AmdgpuMetrics::from_v1_x(crev, buf)),
+            (2, 0) => Some(AmdgpuMetrics::from_v2_0(buf)),
+            (2, crev) if crev <= 4 => Some(AmdgpuMetrics::from_v2_x(crev, buf)),
+            (3, 0) => Some(AmdgpuMetrics::from_v3_0(buf)),
+            (frev, crev) => {
+                debug!("INF: unsupported gpu_metrics v{}.{}, ignoring.",
+                    frev, crev);
+                None
+            }
+        }
+    }
+
+    pub fn from(fpath: &Path) -> Result<Option<AmdgpuMetrics>>
+    {
+        let buf = fs::read(fpath)?;
+
+        Ok(AmdgpuMetrics::from_bytes(&buf))
+    }
+}
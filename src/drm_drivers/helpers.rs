@@ -67,3 +67,22 @@ impl<T> ::std::fmt::Debug for __IncompleteArrayField<T> {
     }
 }
 // end of code generated by rust-bindgen 0.69.4
+
+// from Linux kernel uapi drm.h
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct drm_version {
+    pub version_major: libc::c_int,
+    pub version_minor: libc::c_int,
+    pub version_patchlevel: libc::c_int,
+    pub name_len: libc::size_t,
+    pub name: *mut libc::c_char,
+    pub date_len: libc::size_t,
+    pub date: *mut libc::c_char,
+    pub desc_len: libc::size_t,
+    pub desc: *mut libc::c_char,
+}
+
+// DRM core ioctls don't use the driver commands base
+pub const DRM_IOCTL_VERSION: u64 = ::nix::request_code_readwrite!(
+    DRM_IOCTL_BASE, 0x00, ::std::mem::size_of::<drm_version>());
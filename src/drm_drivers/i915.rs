@@ -21,9 +21,11 @@ use crate::drm_drivers::{
     intel_power::{GpuPowerIntel, IGpuPowerIntel, DGpuPowerIntel},
 };
 use crate::drm_devices::{
-    DrmDeviceType, DrmDeviceFreqs, DrmDeviceFreqLimits,
-    DrmDeviceThrottleReasons, DrmDevicePower, DrmDeviceMemInfo, DrmDeviceInfo
+    DrmDeviceType, DrmDeviceCapabilities, DrmDeviceFreqs, DrmDeviceFreqLimits,
+    DrmDeviceThrottleReasons, DrmDevicePower, DrmDevicePowerLimit,
+    DrmDeviceMemInfo, DrmDeviceTemperature, DrmDeviceFan, DrmDeviceInfo
 };
+use crate::hwmon::Hwmon;
 use crate::drm_fdinfo::DrmMemRegion;
 use crate::drm_clients::DrmClientMemInfo;
 
@@ -103,6 +105,7 @@ pub struct DrmDriveri915
     dev_type: Option<DrmDeviceType>,
     freq_limits: Option<DrmDeviceFreqLimits>,
     power: Option<Box<dyn GpuPowerIntel>>,
+    hwmon: Option<Hwmon>,
 }
 
 impl DrmDriver for DrmDriveri915
@@ -207,6 +210,19 @@ impl DrmDriver for DrmDriveri915
         Ok(qmdmi)
     }
 
+    fn capabilities(&mut self) -> Result<DrmDeviceCapabilities>
+    {
+        Ok(DrmDeviceCapabilities {
+            smem_used: false,  // i915 doesn't track it
+            vram: self.dev_type()?.is_discrete(),
+            freqs: true,
+            power: self.power.is_some(),
+            temps: !self.temps()?.is_empty(),
+            fans: !self.fans()?.is_empty(),
+            client_mem_info: true,
+        })
+    }
+
     fn freq_limits(&mut self) -> Result<DrmDeviceFreqLimits>
     {
         if let Some(fls) = &self.freq_limits {
@@ -229,6 +245,7 @@ impl DrmDriver for DrmDriveri915
             minimum: rpn_val,
             efficient: rp1_val,
             maximum: rp0_val,
+            ..DrmDeviceFreqLimits::new()
         };
 
         self.freq_limits = Some(fls.clone());
@@ -298,6 +315,7 @@ impl DrmDriver for DrmDriveri915
             act_freq: act_val,
             max_freq: max_val,
             throttle_reasons: throttle,
+            ..DrmDeviceFreqs::new()
         })
     }
 
@@ -310,6 +328,36 @@ impl DrmDriver for DrmDriveri915
         self.power.as_mut().unwrap().power_usage()
     }
 
+    fn power_limits(&mut self) -> Result<Vec<DrmDevicePowerLimit>>
+    {
+        if let Some(hwmon) = &self.hwmon {
+            return hwmon.power_limits();
+        }
+        if self.dev_type()?.is_integrated() {
+            return IGpuPowerIntel::power_limits();
+        }
+
+        Ok(Vec::new())
+    }
+
+    fn temps(&mut self) -> Result<Vec<DrmDeviceTemperature>>
+    {
+        if self.hwmon.is_none() {
+            return Ok(Vec::new());
+        }
+
+        self.hwmon.as_ref().unwrap().temps()
+    }
+
+    fn fans(&mut self) -> Result<Vec<DrmDeviceFan>>
+    {
+        if self.hwmon.is_none() {
+            return Ok(Vec::new());
+        }
+
+        self.hwmon.as_ref().unwrap().fans()
+    }
+
     fn client_mem_info(&mut self,
         mem_regs: &HashMap<String, DrmMemRegion>) -> Result<DrmClientMemInfo>
     {
@@ -354,6 +402,7 @@ impl DrmDriveri915
             dev_type: None,
             freq_limits: None,
             power: None,
+            hwmon: None,
         };
 
         let dtype = i915.dev_type()?;
@@ -365,6 +414,9 @@ impl DrmDriveri915
         } else {
             None
         };
+        if dtype.is_discrete() {
+            i915.hwmon = Hwmon::from_dev_dir(&Path::new(&cpath).join("device"))?;
+        }
 
         Ok(Rc::new(RefCell::new(i915)))
     }
@@ -1,6 +1,6 @@
 use core::fmt::Debug;
 use std::collections::HashMap;
-use std::path::{Path, PathBuf};
+use std::path::Path;
 use std::fs::{self, File};
 use std::os::fd::{RawFd, AsRawFd};
 use std::time;
@@ -15,7 +15,7 @@ use crate::perf_event::{
     perf_event_attr, PERF_SAMPLE_IDENTIFIER, PERF_FORMAT_GROUP, PerfEvent
 };
 use crate::hwmon::Hwmon;
-use crate::drm_devices::DrmDevicePower;
+use crate::drm_devices::{DrmDevicePower, DrmDevicePowerLimit};
 
 
 pub trait GpuPowerIntel
@@ -100,6 +100,7 @@ impl DGpuPowerIntel
         Ok(DrmDevicePower {
             gpu_cur_power: gpu_pwr,
             pkg_cur_power: pkg_pwr,
+            ..DrmDevicePower::new()
         })
     }
 
@@ -134,12 +135,14 @@ impl DGpuPowerIntel
         self.last_gpu_val = gpu_val;
         self.last_pkg_val = pkg_val;
 
-        let gpu_pwr = (self.delta_gpu_val as f64 / 1000000.0) / delta_time;
-        let pkg_pwr = (self.delta_pkg_val as f64 / 1000000.0) / delta_time;
+        let gpu_nrg = self.delta_gpu_val as f64 / 1000000.0;
+        let pkg_nrg = self.delta_pkg_val as f64 / 1000000.0;
 
         Ok(DrmDevicePower {
-            gpu_cur_power: gpu_pwr,
-            pkg_cur_power: pkg_pwr,
+            gpu_cur_power: gpu_nrg / delta_time,
+            pkg_cur_power: pkg_nrg / delta_time,
+            gpu_energy: gpu_nrg,
+            pkg_energy: pkg_nrg,
         })
     }
 
@@ -203,21 +206,9 @@ impl DGpuPowerIntel
         false
     }
 
-    pub fn from(dev_dir: &PathBuf) -> Result<Option<Box<dyn GpuPowerIntel>>>
+    pub fn from(dev_dir: &Path) -> Result<Option<Box<dyn GpuPowerIntel>>>
     {
-        let base_dir = dev_dir.join("hwmon");
-        let hwmon_path = fs::read_dir(base_dir)?
-            .into_iter()
-            .filter(|r| r.is_ok())
-            .map(|r| r.unwrap().path())
-            .find(|r| r.file_name().unwrap()
-                .to_str().unwrap().starts_with("hwmon"));
-        if hwmon_path.is_none() {
-            debug!("INF: no {:?}/hwmon* directory, aborting.", dev_dir);
-            return Ok(None);
-        }
-
-        let hwmon = Hwmon::from(hwmon_path.unwrap().to_path_buf())?;
+        let hwmon = Hwmon::from_dev_dir(dev_dir)?;
         if hwmon.is_none() {
             debug!("INF: no Hwmon support, no dGPU power reporting.");
             return Ok(None);
@@ -398,20 +389,77 @@ impl GpuPowerIntel for IGpuPowerIntel
         self.last_gpu_val = gpu_val;
         self.last_pkg_val = pkg_val;
 
-        let gpu_pwr = (self.delta_gpu_val as f64 * self.gpu_scale) /
-            delta_time;
-        let pkg_pwr = (self.delta_pkg_val as f64 * self.pkg_scale) /
-            delta_time;
+        let gpu_nrg = self.delta_gpu_val as f64 * self.gpu_scale;
+        let pkg_nrg = self.delta_pkg_val as f64 * self.pkg_scale;
 
         Ok(DrmDevicePower {
-            gpu_cur_power: gpu_pwr,
-            pkg_cur_power: pkg_pwr,
+            gpu_cur_power: gpu_nrg / delta_time,
+            pkg_cur_power: pkg_nrg / delta_time,
+            gpu_energy: gpu_nrg,
+            pkg_energy: pkg_nrg,
         })
     }
 }
 
 impl IGpuPowerIntel
 {
+    fn rapl_zone_limits(zone_dir: &Path, zname: &str,
+        res: &mut Vec<DrmDevicePowerLimit>) -> Result<()>
+    {
+        for idx in 0.. {
+            let cname = zone_dir.join(format!("constraint_{}_name", idx));
+            if !cname.exists() {
+                break;
+            }
+            let lim_file = zone_dir.join(
+                format!("constraint_{}_power_limit_uw", idx));
+            let val: u64 = match fs::read_to_string(&lim_file) {
+                Ok(st) => st.trim().parse()?,
+                Err(_) => continue,
+            };
+            if val == 0 {
+                continue;
+            }
+
+            let lname = match fs::read_to_string(&cname)?.trim() {
+                "long_term" => String::from("PL1"),
+                "short_term" => String::from("PL2"),
+                "peak_power" => String::from("PL4"),
+                other => other.to_uppercase(),
+            };
+            res.push(DrmDevicePowerLimit::from(
+                &format!("{} {}", zname, lname), val as f64 / 1000000.0));
+        }
+
+        Ok(())
+    }
+
+    pub fn power_limits() -> Result<Vec<DrmDevicePowerLimit>>
+    {
+        let mut res = Vec::new();
+
+        // RAPL package zone and its "uncore" (GPU) subzone, if any
+        let pkg_dir = Path::new("/sys/class/powercap/intel-rapl:0");
+        if !pkg_dir.is_dir() {
+            debug!("INF: no RAPL powercap zone, no power limits.");
+            return Ok(res);
+        }
+        IGpuPowerIntel::rapl_zone_limits(pkg_dir, "PKG", &mut res)?;
+
+        for et in fs::read_dir(pkg_dir)? {
+            let zpath = et?.path();
+            let npath = zpath.join("name");
+            if !zpath.is_dir() || !npath.exists() {
+                continue;
+            }
+            if fs::read_to_string(&npath)?.trim() == "uncore" {
+                IGpuPowerIntel::rapl_zone_limits(&zpath, "GPU", &mut res)?;
+            }
+        }
+
+        Ok(res)
+    }
+
     fn get_perf_config(evt_dir: &Path, name: &str) -> Result<Option<u64>>
     {
         let raw = fs::read_to_string(evt_dir.join(name))?;
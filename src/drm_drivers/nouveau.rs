@@ -0,0 +1,271 @@
+#![allow(dead_code)]
+#![allow(non_camel_case_types)]
+#![allow(non_upper_case_globals)]
+
+use std::collections::HashMap;
+use std::path::Path;
+use std::fs::File;
+use std::os::fd::{RawFd, AsRawFd};
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::mem;
+use std::io;
+
+use anyhow::Result;
+use log::{debug, warn};
+use libc;
+
+use crate::drm_drivers::DrmDriver;
+use crate::drm_drivers::helpers::drm_iowr;
+use crate::hwmon::Hwmon;
+use crate::drm_devices::{
+    DrmDeviceType, DrmDeviceCapabilities, DrmDevicePower, DrmDevicePowerLimit,
+    DrmDeviceMemInfo, DrmDeviceTemperature, DrmDeviceFan, DrmDeviceInfo
+};
+use crate::drm_fdinfo::DrmMemRegion;
+use crate::drm_clients::DrmClientMemInfo;
+
+
+// from Linux kernel uapi nouveau_drm.h
+const NOUVEAU_GETPARAM_PCI_VENDOR: u64 = 3;
+const NOUVEAU_GETPARAM_PCI_DEVICE: u64 = 4;
+const NOUVEAU_GETPARAM_BUS_TYPE: u64 = 5;
+const NOUVEAU_GETPARAM_FB_SIZE: u64 = 8;
+const NOUVEAU_GETPARAM_AGP_SIZE: u64 = 9;
+const NOUVEAU_GETPARAM_CHIPSET_ID: u64 = 11;
+const NOUVEAU_GETPARAM_VM_VRAM_BASE: u64 = 12;
+const NOUVEAU_GETPARAM_GRAPH_UNITS: u64 = 13;
+const NOUVEAU_GETPARAM_PTIMER_TIME: u64 = 14;
+const NOUVEAU_GETPARAM_HAS_BO_USAGE: u64 = 15;
+const NOUVEAU_GETPARAM_HAS_PAGEFLIP: u64 = 16;
+const NOUVEAU_GETPARAM_EXEC_PUSH_MAX: u64 = 17;
+const NOUVEAU_GETPARAM_VRAM_BAR_SIZE: u64 = 18;
+const NOUVEAU_GETPARAM_VRAM_USED: u64 = 19;
+
+// values returned for NOUVEAU_GETPARAM_BUS_TYPE
+const NOUVEAU_BUS_AGP: u64 = 0;
+const NOUVEAU_BUS_PCI: u64 = 1;
+const NOUVEAU_BUS_PCIE: u64 = 2;
+const NOUVEAU_BUS_TEGRA: u64 = 3;
+
+// chipsets of nForce/MCP integrated GPUs
+const NOUVEAU_IGP_CHIPSETS: &[u64] = &[
+    0x4c, 0x4e, 0x63, 0x67, 0x68, 0xaa, 0xac, 0xaf,
+];
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+struct drm_nouveau_getparam {
+    param: u64,
+    value: u64,
+}
+
+const DRM_NOUVEAU_GETPARAM: u64 = 0x00;
+const DRM_IOCTL_NOUVEAU_GETPARAM: u64 = drm_iowr!(DRM_NOUVEAU_GETPARAM,
+    mem::size_of::<drm_nouveau_getparam>());
+
+#[derive(Debug)]
+pub struct DrmDriverNouveau
+{
+    _dn_file: File,
+    dn_fd: RawFd,
+    dev_type: Option<DrmDeviceType>,
+    hwmon: Option<Hwmon>,
+    sensor: String,
+}
+
+impl DrmDriver for DrmDriverNouveau
+{
+    fn name(&self) -> &str
+    {
+        "nouveau"
+    }
+
+    fn dev_type(&mut self) -> Result<DrmDeviceType>
+    {
+        if let Some(dt) = &self.dev_type {
+            return Ok(dt.clone());
+        }
+
+        let bus = self.nouveau_getparam(NOUVEAU_GETPARAM_BUS_TYPE)?;
+        let chipset = self.nouveau_getparam(NOUVEAU_GETPARAM_CHIPSET_ID)?;
+        debug!("INF: nouveau device: vendor {:#x}, device {:#x}, \
+            chipset {:#x}, bus type {:?}.",
+            self.nouveau_getparam(NOUVEAU_GETPARAM_PCI_VENDOR).unwrap_or(0),
+            self.nouveau_getparam(NOUVEAU_GETPARAM_PCI_DEVICE).unwrap_or(0),
+            chipset, bus);
+
+        let qmdt = if bus == NOUVEAU_BUS_TEGRA ||
+            NOUVEAU_IGP_CHIPSETS.contains(&chipset) {
+            DrmDeviceType::Integrated
+        } else {
+            DrmDeviceType::Discrete
+        };
+
+        self.dev_type = Some(qmdt.clone());
+        Ok(qmdt)
+    }
+
+    fn capabilities(&mut self) -> Result<DrmDeviceCapabilities>
+    {
+        Ok(DrmDeviceCapabilities {
+            smem_used: false,  // nouveau doesn't track it
+            vram: self.dev_type()?.is_discrete(),
+            freqs: false,
+            power: !self.sensor.is_empty(),
+            temps: !self.temps()?.is_empty(),
+            fans: !self.fans()?.is_empty(),
+            client_mem_info: true,
+        })
+    }
+
+    fn mem_info(&mut self) -> Result<DrmDeviceMemInfo>
+    {
+        let mut si: libc::sysinfo = unsafe { mem::zeroed() };
+        let res = unsafe { libc::sysinfo(&mut si) };
+        if res < 0 {
+            return Err(io::Error::last_os_error().into());
+        }
+
+        // VRAM used query only exists on newer kernels
+        let vram_used = self.nouveau_getparam(
+            NOUVEAU_GETPARAM_VRAM_USED).unwrap_or(0);
+
+        Ok(DrmDeviceMemInfo {
+            smem_total: si.totalram as u64 * si.mem_unit as u64,
+            smem_used: 0,
+            vram_total: self.nouveau_getparam(NOUVEAU_GETPARAM_FB_SIZE)?,
+            vram_used,
+        })
+    }
+
+    fn power(&mut self) -> Result<DrmDevicePower>
+    {
+        if self.hwmon.is_none() || self.sensor.is_empty() {
+            return Ok(DrmDevicePower::new());
+        }
+        let hwmon = self.hwmon.as_ref().unwrap();
+
+        let val = hwmon.read_sensor(&self.sensor, "input")?;
+
+        Ok(DrmDevicePower {
+            gpu_cur_power: val as f64 / 1000000.0,
+            ..DrmDevicePower::new()
+        })
+    }
+
+    fn power_limits(&mut self) -> Result<Vec<DrmDevicePowerLimit>>
+    {
+        if self.hwmon.is_none() {
+            return Ok(Vec::new());
+        }
+
+        self.hwmon.as_ref().unwrap().power_limits()
+    }
+
+    fn temps(&mut self) -> Result<Vec<DrmDeviceTemperature>>
+    {
+        if self.hwmon.is_none() {
+            return Ok(Vec::new());
+        }
+
+        self.hwmon.as_ref().unwrap().temps()
+    }
+
+    fn fans(&mut self) -> Result<Vec<DrmDeviceFan>>
+    {
+        if self.hwmon.is_none() {
+            return Ok(Vec::new());
+        }
+
+        self.hwmon.as_ref().unwrap().fans()
+    }
+
+    fn client_mem_info(&mut self,
+        mem_regs: &HashMap<String, DrmMemRegion>) -> Result<DrmClientMemInfo>
+    {
+        let mut cmi = DrmClientMemInfo::new();
+        let is_discrete = self.dev_type()?.is_discrete();
+
+        for mr in mem_regs.values() {
+            // "memory" is the generic region for drivers without placement
+            if mr.name.starts_with("vram") ||
+                (mr.name == "memory" && is_discrete) {
+                cmi.vram_used += mr.total;
+                cmi.vram_rss += mr.resident;
+            } else if mr.name.starts_with("gart") ||
+                mr.name.starts_with("gtt") || mr.name.starts_with("cpu") ||
+                mr.name.starts_with("system") || mr.name == "memory" {
+                cmi.smem_used += mr.total;
+                cmi.smem_rss += mr.resident;
+            } else {
+                warn!("Unknown nouveau memory region: {:?}, skpping it.",
+                    mr.name);
+                continue;
+            }
+        }
+
+        Ok(cmi)
+    }
+}
+
+impl DrmDriverNouveau
+{
+    fn nouveau_getparam(&self, param: u64) -> Result<u64>
+    {
+        let mut gp = drm_nouveau_getparam {
+            param,
+            value: 0,
+        };
+
+        let res = unsafe {
+            libc::ioctl(self.dn_fd, DRM_IOCTL_NOUVEAU_GETPARAM, &mut gp) };
+        if res < 0 {
+            return Err(io::Error::last_os_error().into());
+        }
+
+        Ok(gp.value)
+    }
+
+    pub fn new(qmd: &DrmDeviceInfo) -> Result<Rc<RefCell<dyn DrmDriver>>>
+    {
+        let mut dn: &str = &qmd.drm_minors[0].devnode;
+        for c in qmd.drm_minors.iter() {
+            if c.devnode.contains("render") {
+                dn = &c.devnode;
+                break;
+            }
+        }
+
+        let file = File::open(dn)?;
+        let fd = file.as_raw_fd();
+
+        let mut cpath = String::from("/sys/class/drm/");
+        let card = Path::new(dn).file_name().unwrap().to_str().unwrap();
+        cpath.push_str(card);
+
+        let mut nouveau = DrmDriverNouveau {
+            _dn_file: file,
+            dn_fd: fd,
+            dev_type: None,
+            hwmon: None,
+            sensor: String::new(),
+        };
+
+        nouveau.dev_type()?;
+
+        if let Some(hwmon) = Hwmon::from_dev_dir(
+            &Path::new(&cpath).join("device"))? {
+            let plist = hwmon.sensors("power");
+            for s in plist.iter() {
+                if s.has_item("input") {
+                    nouveau.sensor = s.sensor.clone();
+                    break;
+                }
+            }
+            nouveau.hwmon = Some(hwmon);
+        }
+
+        Ok(Rc::new(RefCell::new(nouveau)))
+    }
+}
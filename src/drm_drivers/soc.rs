@@ -0,0 +1,155 @@
+use std::collections::HashMap;
+use std::path::Path;
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::mem;
+use std::io;
+
+use anyhow::Result;
+use log::{debug, warn};
+use libc;
+
+use crate::drm_drivers::DrmDriver;
+use crate::devfreq::Devfreq;
+use crate::drm_devices::{
+    DrmDeviceType, DrmDeviceCapabilities, DrmDeviceFreqLimits, DrmDeviceFreqs,
+    DrmDeviceDevfreq, DrmDeviceMemInfo, DrmDeviceInfo
+};
+use crate::drm_fdinfo::DrmMemRegion;
+use crate::drm_clients::DrmClientMemInfo;
+
+
+// Arm/SoC GPUs are platform devices sharing system memory with the CPU,
+// they report engines and memory through the common DRM fdinfo keys
+// (e.g. drm-engine-fragment on panfrost, drm-engine-panthor on panthor,
+// drm-engine-gpu on msm, drm-engine-bin/render/csd on v3d, drm-engine-gp/pp
+// on lima, mapped to engine classes in app_data::ENGINE_CLASSES) and their
+// frequencies through devfreq.
+#[derive(Debug)]
+pub struct DrmDriverSoc
+{
+    drv_name: String,
+    devfreq: Option<Devfreq>,
+    freq_limits: Option<DrmDeviceFreqLimits>,
+}
+
+impl DrmDriver for DrmDriverSoc
+{
+    fn name(&self) -> &str
+    {
+        &self.drv_name
+    }
+
+    fn dev_type(&mut self) -> Result<DrmDeviceType>
+    {
+        Ok(DrmDeviceType::Integrated)
+    }
+
+    fn capabilities(&mut self) -> Result<DrmDeviceCapabilities>
+    {
+        Ok(DrmDeviceCapabilities {
+            smem_used: false,  // no device-wide accounting
+            vram: false,
+            freqs: self.devfreq.is_some(),
+            power: false,
+            temps: false,
+            fans: false,
+            client_mem_info: true,
+        })
+    }
+
+    fn freq_limits(&mut self) -> Result<DrmDeviceFreqLimits>
+    {
+        if let Some(fls) = &self.freq_limits {
+            return Ok(fls.clone());
+        }
+
+        let fls = if let Some(df) = &self.devfreq {
+            df.freq_limits()? } else { DrmDeviceFreqLimits::new() };
+
+        self.freq_limits = Some(fls.clone());
+        Ok(fls)
+    }
+
+    fn freqs(&mut self) -> Result<DrmDeviceFreqs>
+    {
+        if self.devfreq.is_none() {
+            return Ok(DrmDeviceFreqs::new());
+        }
+
+        self.devfreq.as_ref().unwrap().freqs()
+    }
+
+    fn devfreq(&mut self) -> Result<Option<DrmDeviceDevfreq>>
+    {
+        if self.devfreq.is_none() {
+            return Ok(None);
+        }
+
+        Ok(Some(self.devfreq.as_ref().unwrap().stats()?))
+    }
+
+    fn mem_info(&mut self) -> Result<DrmDeviceMemInfo>
+    {
+        let mut si: libc::sysinfo = unsafe { mem::zeroed() };
+        let res = unsafe { libc::sysinfo(&mut si) };
+        if res < 0 {
+            return Err(io::Error::last_os_error().into());
+        }
+
+        Ok(DrmDeviceMemInfo {
+            smem_total: si.totalram as u64 * si.mem_unit as u64,
+            ..DrmDeviceMemInfo::new()
+        })
+    }
+
+    fn client_mem_info(&mut self,
+        mem_regs: &HashMap<String, DrmMemRegion>) -> Result<DrmClientMemInfo>
+    {
+        let mut cmi = DrmClientMemInfo::new();
+
+        for mr in mem_regs.values() {
+            // "memory" is the generic region from drm_show_memory_stats()
+            if mr.name == "memory" || mr.name.starts_with("system") {
+                cmi.smem_used += mr.total;
+                cmi.smem_rss += mr.resident;
+            } else {
+                warn!("Unknown {} memory region: {:?}, skipping it.",
+                    self.drv_name, mr.name);
+                continue;
+            }
+        }
+
+        Ok(cmi)
+    }
+}
+
+impl DrmDriverSoc
+{
+    pub fn new(qmd: &DrmDeviceInfo) -> Result<Rc<RefCell<dyn DrmDriver>>>
+    {
+        let card = Path::new(&qmd.drm_minors[0].devnode)
+            .file_name().unwrap().to_str().unwrap();
+        let dev_dir = Path::new("/sys/class/drm").join(card).join("device");
+
+        let mut devfreq = Devfreq::from_dev_dir(&dev_dir)?;
+        if devfreq.is_none() && qmd.drv_name == "msm" {
+            // msm DRM device is the display subsystem, not the GPU
+            devfreq = Devfreq::from_name_suffix(".gpu")?;
+        }
+        if devfreq.is_none() {
+            debug!("INF: no devfreq for {:?}, no freqs reporting.",
+                qmd.pci_dev);
+        }
+
+        let mut soc = DrmDriverSoc {
+            drv_name: qmd.drv_name.clone(),
+            devfreq,
+            freq_limits: None,
+        };
+
+        soc.freq_limits()?;
+
+        Ok(Rc::new(RefCell::new(soc)))
+    }
+}
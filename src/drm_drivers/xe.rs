@@ -21,9 +21,12 @@ use crate::drm_drivers::{
     intel_power::{GpuPowerIntel, IGpuPowerIntel, DGpuPowerIntel},
 };
 use crate::drm_devices::{
-    DrmDeviceType, DrmDeviceFreqLimits, DrmDeviceFreqs,
-    DrmDeviceThrottleReasons, DrmDevicePower, DrmDeviceMemInfo, DrmDeviceInfo
+    DrmDeviceType, DrmDeviceCapabilities, DrmDeviceFreqLimits, DrmDeviceFreqs,
+    DrmDeviceThrottleReasons, DrmDevicePower, DrmDevicePowerLimit,
+    DrmDeviceMemInfo, DrmDeviceTemperature, DrmDeviceFan,
+    DrmDeviceVfProvisioning, DrmDeviceFirmware, DrmDeviceInfo
 };
+use crate::hwmon::Hwmon;
 use crate::drm_fdinfo::DrmMemRegion;
 use crate::drm_clients::DrmClientMemInfo;
 
@@ -72,6 +75,24 @@ const DRM_XE_QUERY_CONFIG_MAX_EXEC_QUEUE_PRIORITY: u32 = 4;
 
 const DRM_XE_DEVICE_QUERY_CONFIG: u32 = 2;
 
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+struct drm_xe_query_uc_fw_version {
+    uc_type: u16,
+    pad: u16,
+    branch_ver: u32,
+    major_ver: u32,
+    minor_ver: u32,
+    patch_ver: u32,
+    pad2: u32,
+    reserved: u64,
+}
+
+const XE_QUERY_UC_TYPE_GUC_SUBMISSION: u16 = 0;
+const XE_QUERY_UC_TYPE_HUC: u16 = 1;
+
+const DRM_XE_DEVICE_QUERY_UC_FW_VERSION: u32 = 7;
+
 #[repr(C)]
 #[derive(Debug, Copy, Clone)]
 struct drm_xe_device_query {
@@ -96,6 +117,8 @@ pub struct DrmDriverXe
     dev_type: Option<DrmDeviceType>,
     freq_limits: Option<DrmDeviceFreqLimits>,
     power: Option<Box<dyn GpuPowerIntel>>,
+    hwmon: Option<Hwmon>,
+    debugfs_dir: Option<PathBuf>,
 }
 
 impl DrmDriver for DrmDriverXe
@@ -229,6 +252,19 @@ impl DrmDriver for DrmDriverXe
         Ok(qmdmi)
     }
 
+    fn capabilities(&mut self) -> Result<DrmDeviceCapabilities>
+    {
+        Ok(DrmDeviceCapabilities {
+            smem_used: true,
+            vram: self.dev_type()?.is_discrete(),
+            freqs: true,
+            power: self.power.is_some(),
+            temps: !self.temps()?.is_empty(),
+            fans: !self.fans()?.is_empty(),
+            client_mem_info: true,
+        })
+    }
+
     fn freq_limits(&mut self) -> Result<DrmDeviceFreqLimits>
     {
         if let Some(fls) = &self.freq_limits {
@@ -251,6 +287,7 @@ impl DrmDriver for DrmDriverXe
             minimum: rpn_val,
             efficient: rpe_val,
             maximum: rp0_val,
+            ..DrmDeviceFreqLimits::new()
         };
 
         self.freq_limits = Some(fls.clone());
@@ -320,6 +357,7 @@ impl DrmDriver for DrmDriverXe
             act_freq: act_val,
             max_freq: max_val,
             throttle_reasons: throttle,
+            ..DrmDeviceFreqs::new()
         })
     }
 
@@ -332,6 +370,91 @@ impl DrmDriver for DrmDriverXe
         self.power.as_mut().unwrap().power_usage()
     }
 
+    fn power_limits(&mut self) -> Result<Vec<DrmDevicePowerLimit>>
+    {
+        if let Some(hwmon) = &self.hwmon {
+            return hwmon.power_limits();
+        }
+        if self.dev_type()?.is_integrated() {
+            return IGpuPowerIntel::power_limits();
+        }
+
+        Ok(Vec::new())
+    }
+
+    fn temps(&mut self) -> Result<Vec<DrmDeviceTemperature>>
+    {
+        if self.hwmon.is_none() {
+            return Ok(Vec::new());
+        }
+
+        self.hwmon.as_ref().unwrap().temps()
+    }
+
+    fn fans(&mut self) -> Result<Vec<DrmDeviceFan>>
+    {
+        if self.hwmon.is_none() {
+            return Ok(Vec::new());
+        }
+
+        self.hwmon.as_ref().unwrap().fans()
+    }
+
+    fn firmwares(&mut self) -> Result<Vec<DrmDeviceFirmware>>
+    {
+        let mut fws = Vec::new();
+
+        for (name, uc_type) in [("GuC", XE_QUERY_UC_TYPE_GUC_SUBMISSION),
+            ("HuC", XE_QUERY_UC_TYPE_HUC)] {
+            // older kernels only have it in debugfs
+            if let Ok(ver) = self.xe_uc_fw_version(uc_type) {
+                fws.push(DrmDeviceFirmware::from(name, &ver));
+            } else if let Some(ver) = self.debugfs_uc_version(
+                &name.to_lowercase()) {
+                fws.push(DrmDeviceFirmware::from(name, &ver));
+            }
+        }
+        if let Some(ver) = self.debugfs_uc_version("gsc") {
+            fws.push(DrmDeviceFirmware::from("GSC", &ver));
+        }
+
+        Ok(fws)
+    }
+
+    fn vf_provisioning(&mut self,
+        nr_vfs: u32) -> Result<Vec<DrmDeviceVfProvisioning>>
+    {
+        let mut res = Vec::new();
+        if self.debugfs_dir.is_none() {
+            return Ok(res);
+        }
+        let dfs_dir = self.debugfs_dir.as_ref().unwrap();
+
+        // TODO: handle more than one tile & gt
+        for vf_id in 1..=nr_vfs {
+            let vfn = format!("vf{}", vf_id);
+            // GGTT & LMEM quotas moved from gt to tile dirs on newer kernels
+            let dirs = [dfs_dir.join("tile0").join(&vfn),
+                dfs_dir.join("gt0").join(&vfn)];
+
+            res.push(DrmDeviceVfProvisioning {
+                vf_id,
+                ggtt_quota: DrmDriverXe::read_vf_param(&dirs, "ggtt_quota"),
+                lmem_quota: DrmDriverXe::read_vf_param(&dirs, "lmem_quota"),
+                contexts_quota: DrmDriverXe::read_vf_param(
+                    &dirs, "contexts_quota"),
+                doorbells_quota: DrmDriverXe::read_vf_param(
+                    &dirs, "doorbells_quota"),
+                exec_quantum_ms: DrmDriverXe::read_vf_param(
+                    &dirs, "exec_quantum_ms"),
+                preempt_timeout_us: DrmDriverXe::read_vf_param(
+                    &dirs, "preempt_timeout_us"),
+            });
+        }
+
+        Ok(res)
+    }
+
     fn client_mem_info(&mut self,
         mem_regs: &HashMap<String, DrmMemRegion>) -> Result<DrmClientMemInfo>
     {
@@ -364,6 +487,64 @@ impl DrmDriver for DrmDriverXe
 
 impl DrmDriverXe
 {
+    fn xe_uc_fw_version(&self, uc_type: u16) -> Result<String>
+    {
+        let mut ucv: drm_xe_query_uc_fw_version = unsafe { mem::zeroed() };
+        ucv.uc_type = uc_type;
+        let ucv_ptr: *mut drm_xe_query_uc_fw_version = &mut ucv;
+
+        let mut dq = drm_xe_device_query {
+            extensions: 0,
+            query: DRM_XE_DEVICE_QUERY_UC_FW_VERSION,
+            size: mem::size_of::<drm_xe_query_uc_fw_version>() as u32,
+            data: ucv_ptr as u64,
+            reserved: [0, 0],
+        };
+
+        let res = unsafe {
+            libc::ioctl(self.dn_fd, DRM_IOCTL_XE_DEVICE_QUERY, &mut dq) };
+        if res < 0 {
+            return Err(io::Error::last_os_error().into());
+        }
+
+        Ok(format!("{}.{}.{}.{}", ucv.branch_ver,
+            ucv.major_ver, ucv.minor_ver, ucv.patch_ver))
+    }
+
+    // parses "version: wanted X, found Y" from gt*/uc/<uc>_info files
+    fn debugfs_uc_version(&self, uc: &str) -> Option<String>
+    {
+        let dfs_dir = self.debugfs_dir.as_ref()?;
+
+        for gt in 0..4 {
+            let fpath = dfs_dir.join(format!("gt{}/uc/{}_info", gt, uc));
+            if let Ok(info) = fs::read_to_string(fpath) {
+                for line in info.lines() {
+                    let tl = line.trim();
+                    if !tl.starts_with("version:") {
+                        continue;
+                    }
+                    if let Some((_, found)) = tl.split_once("found ") {
+                        return Some(found.trim().to_string());
+                    }
+                }
+            }
+        }
+
+        None
+    }
+
+    fn read_vf_param(dirs: &[PathBuf], fname: &str) -> Option<u64>
+    {
+        for d in dirs.iter() {
+            if let Ok(val) = fs::read_to_string(d.join(fname)) {
+                return val.trim().parse().ok();
+            }
+        }
+
+        None
+    }
+
     pub fn new(qmd: &DrmDeviceInfo) -> Result<Rc<RefCell<dyn DrmDriver>>>
     {
         let file = File::open(qmd.drm_minors[0].devnode.clone())?;
@@ -384,6 +565,8 @@ impl DrmDriverXe
             dev_type: None,
             freq_limits: None,
             power: None,
+            hwmon: None,
+            debugfs_dir: None,
         };
 
         let dtype = xe.dev_type()?;
@@ -395,6 +578,19 @@ impl DrmDriverXe
         } else {
             None
         };
+        if dtype.is_discrete() {
+            xe.hwmon = Hwmon::from_dev_dir(&dev_path)?;
+        }
+
+        // SR-IOV PF provisioning is only in debugfs, named after the PCI
+        // device on newer kernels and the DRM card minor before
+        let dfs_base = Path::new("/sys/kernel/debug/dri");
+        for dn in [qmd.pci_dev.clone(), qmd.drm_minors[0].drm_minor.to_string()] {
+            if dfs_base.join(&dn).join("gt0").is_dir() {
+                xe.debugfs_dir = Some(dfs_base.join(&dn));
+                break;
+            }
+        }
 
         Ok(Rc::new(RefCell::new(xe)))
     }
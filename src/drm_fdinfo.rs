@@ -7,6 +7,8 @@ use anyhow::Result;
 use libc;
 use log::debug;
 
+use crate::drm_devices::{DRM_MAJOR, ACCEL_MAJOR};
+
 
 #[derive(Debug)]
 #[allow(dead_code)]
@@ -17,6 +19,8 @@ pub struct DrmEngine
     pub time: u64,
     pub cycles: u64,
     pub total_cycles: u64,
+    pub cur_freq: u64,  // MHz
+    pub max_freq: u64,  // MHz
 }
 
 enum EngKvType
@@ -25,6 +29,8 @@ enum EngKvType
     KvCapacity,
     KvCycles,
     KvTotCycles,
+    KvCurFreq,
+    KvMaxFreq,
 }
 
 impl Default for DrmEngine
@@ -37,6 +43,8 @@ impl Default for DrmEngine
             time: 0,
             cycles: 0,
             total_cycles: 0,
+            cur_freq: 0,
+            max_freq: 0,
         }
     }
 }
@@ -126,13 +134,15 @@ impl Default for DrmFdinfo
 
 impl DrmFdinfo
 {
-    pub fn is_drm_fd(file: &Path, minor: &mut u32) -> Result<bool>
+    pub fn is_drm_fd(file: &Path,
+        major: &mut u32, minor: &mut u32) -> Result<bool>
     {
         let met = fs::metadata(file)?;
         let st_mode = met.st_mode();
         let st_rdev = met.st_rdev();
 
         // check it's char device and major 226 for DRM device
+        // (or major 261 for compute accel device)
         let mj: u32;
         let mn: u32;
         unsafe {
@@ -140,7 +150,9 @@ impl DrmFdinfo
             mn = libc::minor(st_rdev);
         }
 
-        if st_mode & libc::S_IFMT == libc::S_IFCHR && mj == 226 {
+        if st_mode & libc::S_IFMT == libc::S_IFCHR &&
+            (mj == DRM_MAJOR || mj == ACCEL_MAJOR) {
+            *major = mj;
             *minor = mn;
             return Ok(true);
         }
@@ -148,6 +160,14 @@ impl DrmFdinfo
         Ok(false)
     }
 
+    fn sysname_from_minor(major: u32, minor: u32) -> Option<String>
+    {
+        let dpath = format!("/sys/dev/char/{}:{}/device", major, minor);
+        let dev = fs::canonicalize(dpath).ok()?;
+
+        Some(dev.file_name()?.to_str()?.to_string())
+    }
+
     fn update_engine(&mut self, kv_type: EngKvType, eng_name: &str, val: &str) -> Result<()>
     {
         let eng: &mut DrmEngine;
@@ -171,11 +191,31 @@ impl DrmFdinfo
             EngKvType::KvTotCycles => {
                 eng.total_cycles = val.parse()?;
             },
+            EngKvType::KvCurFreq => {
+                eng.cur_freq = DrmFdinfo::mhz_from_freq(val)?;
+            },
+            EngKvType::KvMaxFreq => {
+                eng.max_freq = DrmFdinfo::mhz_from_freq(val)?;
+            },
         }
 
         Ok(())
     }
 
+    // frequencies come as "<value> [Hz|kHz|MHz]", Hz if no unit
+    fn mhz_from_freq(val: &str) -> Result<u64>
+    {
+        let dt: Vec<&str> = val.split_whitespace().collect();
+        let nr: u64 = dt[0].parse()?;
+        let unit = if dt.len() == 2 { dt[1] } else { "Hz" };
+
+        Ok(match unit {
+            "MHz" => nr,
+            "kHz" | "KHz" => nr / 1000,
+            &_ => nr / 1000000,
+        })
+    }
+
     fn mul_from_unit(unit: &str) -> u64
     {
         match unit {
@@ -223,7 +263,8 @@ impl DrmFdinfo
         Ok(())
     }
 
-    pub fn from(fdinfo: &PathBuf, d_minor: u32) -> Result<DrmFdinfo>
+    pub fn from(fdinfo: &PathBuf,
+        d_major: u32, d_minor: u32) -> Result<DrmFdinfo>
     {
         let all_str = fs::read_to_string(fdinfo)?;
 
@@ -266,6 +307,12 @@ impl DrmFdinfo
             } else if k.starts_with("drm-total-cycles-") {
                 let en = &k["drm-total-cycles-".len()..];
                 info.update_engine(EngKvType::KvTotCycles, en, v)?;
+            } else if k.starts_with("drm-curfreq-") {
+                let en = &k["drm-curfreq-".len()..];
+                info.update_engine(EngKvType::KvCurFreq, en, v)?;
+            } else if k.starts_with("drm-maxfreq-") {
+                let en = &k["drm-maxfreq-".len()..];
+                info.update_engine(EngKvType::KvMaxFreq, en, v)?;
             } else if k.starts_with("drm-total-") {
                 let mrn = &k["drm-total-".len()..];
                 info.update_mem_region(MemRegKvType::KvTotal, mrn, v)?;
@@ -284,6 +331,13 @@ impl DrmFdinfo
             }
         }
 
+        // no drm-pdev for non-PCI devices, find it from the DRM minor
+        if info.pci_dev.is_empty() {
+            if let Some(sn) = DrmFdinfo::sysname_from_minor(d_major, d_minor) {
+                info.pci_dev = sn;
+            }
+        }
+
         Ok(info)
     }
 }
@@ -5,6 +5,10 @@ use std::fs;
 use anyhow::Result;
 use log::debug;
 
+use crate::drm_devices::{
+    DrmDevicePowerLimit, DrmDeviceTemperature, DrmDeviceFan
+};
+
 
 #[derive(Debug)]
 pub struct Sensor
@@ -61,6 +65,24 @@ impl Hwmon
         Ok(val)
     }
 
+    // temperatures can be negative, in millidegree Celsius
+    fn read_temp(&self, sty: &str, item: &str) -> Result<f64>
+    {
+        let sfile = format!("{}_{}", sty, item);
+        let spath = self.base_dir.join(sfile);
+        let val: i64 = fs::read_to_string(spath)?.trim().parse()?;
+
+        Ok(val as f64 / 1000.0)
+    }
+
+    fn read_file(&self, fname: &str) -> Result<u64>
+    {
+        let val: u64 = fs::read_to_string(self.base_dir.join(fname))?
+            .trim().parse()?;
+
+        Ok(val)
+    }
+
     pub fn sensors(&self, stype: &str) -> Vec<&Sensor>
     {
         let mut res = Vec::new();
@@ -70,10 +92,136 @@ impl Hwmon
                 res.push(sensor);
             }
         }
+        res.sort_by(|a, b| a.sensor.cmp(&b.sensor));
 
         res
     }
 
+    pub fn power_limits(&self) -> Result<Vec<DrmDevicePowerLimit>>
+    {
+        let mut res = Vec::new();
+        let items = [
+            ("cap", "CAP"),
+            ("max", "MAX"),
+            ("crit", "CRIT"),
+            ("rated_max", "TDP"),
+        ];
+
+        // power*_cap/max/crit/rated_max are in microwatts, 0 means disabled
+        for s in self.sensors("power") {
+            let sname = if s.label.is_empty() {
+                s.sensor.to_uppercase() } else { s.label.to_uppercase() };
+
+            for (item, iname) in items {
+                if !s.has_item(item) {
+                    continue;
+                }
+                let val = match self.read_sensor(&s.sensor, item) {
+                    Ok(v) if v > 0 => v,
+                    _ => continue,
+                };
+
+                res.push(DrmDevicePowerLimit::from(
+                    &format!("{} {}", sname, iname), val as f64 / 1000000.0));
+            }
+        }
+
+        Ok(res)
+    }
+
+    pub fn temps(&self) -> Result<Vec<DrmDeviceTemperature>>
+    {
+        let mut res = Vec::new();
+
+        // temp*_input, temp*_max and temp*_crit are in millidegree Celsius
+        for s in self.sensors("temp") {
+            if !s.has_item("input") {
+                continue;
+            }
+
+            let mut temp = DrmDeviceTemperature::new();
+            temp.name = if s.label.is_empty() {
+                s.sensor.clone() } else { s.label.clone() };
+            // skip unreadable sensors (e.g. powered down) instead of
+            // failing the whole refresh
+            temp.temp = match self.read_temp(&s.sensor, "input") {
+                Ok(val) => val,
+                Err(err) => {
+                    debug!("ERR: failed to read {}_input: {:?}",
+                        s.sensor, err);
+                    continue;
+                }
+            };
+            if s.has_item("max") {
+                if let Ok(val) = self.read_temp(&s.sensor, "max") {
+                    temp.max = val;
+                }
+            }
+            if s.has_item("crit") {
+                if let Ok(val) = self.read_temp(&s.sensor, "crit") {
+                    temp.crit = val;
+                }
+            }
+
+            res.push(temp);
+        }
+
+        Ok(res)
+    }
+
+    pub fn fans(&self) -> Result<Vec<DrmDeviceFan>>
+    {
+        let mut res = Vec::new();
+
+        // fan*_input/target/max are in RPM, pwm* is the duty cycle (0-255)
+        for s in self.sensors("fan") {
+            if !s.has_item("input") {
+                continue;
+            }
+
+            let mut fan = DrmDeviceFan::new();
+            fan.name = if s.label.is_empty() {
+                s.sensor.clone() } else { s.label.clone() };
+            // skip unreadable sensors instead of failing the whole refresh
+            fan.speed = match self.read_sensor(&s.sensor, "input") {
+                Ok(val) => val,
+                Err(err) => {
+                    debug!("ERR: failed to read {}_input: {:?}",
+                        s.sensor, err);
+                    continue;
+                }
+            };
+            if s.has_item("target") {
+                if let Ok(val) = self.read_sensor(&s.sensor, "target") {
+                    fan.target = val;
+                }
+            }
+            if s.has_item("max") {
+                if let Ok(val) = self.read_sensor(&s.sensor, "max") {
+                    fan.max = val;
+                }
+            }
+
+            let pwm = s.sensor.replacen("fan", "pwm", 1);
+            if self.base_dir.join(&pwm).is_file() {
+                let pwm_max = match self.sensors.get(&pwm) {
+                    Some(ps) if ps.has_item("max") =>
+                        self.read_sensor(&pwm, "max").unwrap_or(0),
+                    _ => 255,
+                };
+                if let Ok(pwm_val) = self.read_file(&pwm) {
+                    if pwm_max > 0 {
+                        fan.duty = pwm_val as f64 * 100.0 / pwm_max as f64;
+                    }
+                }
+            }
+
+            res.push(fan);
+        }
+
+        Ok(res)
+    }
+
     pub fn refresh(&mut self) -> Result<()>
     {
         for et in self.base_dir.read_dir()? {
@@ -107,6 +255,27 @@ impl Hwmon
         Ok(())
     }
 
+    pub fn from_dev_dir(dev_dir: &Path) -> Result<Option<Hwmon>>
+    {
+        let base_dir = dev_dir.join("hwmon");
+        if !base_dir.is_dir() {
+            debug!("INF: no {:?} directory, aborting.", base_dir);
+            return Ok(None);
+        }
+
+        let hwmon_path = fs::read_dir(base_dir)?
+            .filter_map(|r| r.ok())
+            .map(|r| r.path())
+            .find(|r| r.file_name().unwrap()
+                .to_str().unwrap().starts_with("hwmon"));
+        if hwmon_path.is_none() {
+            debug!("INF: no {:?}/hwmon/hwmon* directory, aborting.", dev_dir);
+            return Ok(None);
+        }
+
+        Hwmon::from(hwmon_path.unwrap())
+    }
+
     pub fn from(base_dir: PathBuf) -> Result<Option<Hwmon>>
     {
         let npath = base_dir.join("name");
@@ -6,29 +6,94 @@ use std::env;
 
 use anyhow::{bail, Context, Result};
 use env_logger;
-use clap::{Parser, ArgAction};
+use clap::{ArgAction, ArgMatches, CommandFactory, FromArgMatches, Parser, Subcommand};
+use clap::parser::ValueSource;
 use libc;
 
 mod perf_event;
 mod hwmon;
+mod devfreq;
+mod pci_ids;
 mod drm_devices;
 mod drm_drivers;
 mod drm_fdinfo;
 mod proc_info;
 mod drm_clients;
 mod app_data;
+mod app_summary;
+mod app_alerts;
+mod app_check;
+mod app_config;
 mod app;
 
 use drm_devices::DrmDevices;
 use app_data::AppData;
-use app::App;
+use app_alerts::AppAlerts;
+use app_check::{AppCheck, CHECK_FAILED_EXIT_CODE};
+use app_config::AppConfig;
+use app::{App, AppChild};
 
 
+/// limits checked on the stats collected while the command runs
+#[derive(clap::Args, Debug)]
+pub struct CheckLimits {
+    /// max peak GPU power (W) of any device
+    #[arg(long)]
+    pub max_power: Option<f64>,
+
+    /// max average GPU power (W) of any device
+    #[arg(long)]
+    pub max_avg_power: Option<f64>,
+
+    /// max GPU energy (J) used by any device
+    #[arg(long)]
+    pub max_energy: Option<f64>,
+
+    /// max peak VRAM used by the command's DRM clients, e.g. "4G"
+    #[arg(long)]
+    pub max_vram: Option<String>,
+
+    /// max peak system memory used by the command's DRM clients, e.g. "512M"
+    #[arg(long)]
+    pub max_smem: Option<String>,
+
+    /// max percentage of time any device was throttled
+    #[arg(long)]
+    pub max_throttle: Option<f64>,
+
+    /// min average usage (%) per engine class or name on the busiest device, e.g. "render=60"
+    #[arg(long, action = ArgAction::Append)]
+    pub min_avg_engine: Vec<String>,
+
+    /// max average usage (%) per engine class or name on any device, e.g. "copy=20"
+    #[arg(long, action = ArgAction::Append)]
+    pub max_avg_engine: Vec<String>,
+}
+
+#[derive(Subcommand, Debug)]
+enum Commands {
+    /// run a command, tracking only its process tree, and print a summary of its usage when it exits
+    Run {
+        /// command to run and its arguments
+        #[arg(required = true, trailing_var_arg = true, allow_hyphen_values = true)]
+        cmd: Vec<String>,
+    },
+    /// run a command like run does and check its usage against limits, exiting with a non-zero code if any is violated
+    Check {
+        #[command(flatten)]
+        limits: CheckLimits,
+
+        /// command to run and its arguments
+        #[arg(required = true, trailing_var_arg = true, allow_hyphen_values = true)]
+        cmd: Vec<String>,
+    },
+}
+
 /// qmassa! - display DRM clients usage stats
 #[derive(Parser, Debug)]
 #[command(version, about, long_about = None)]
 pub struct Args {
-    /// show only specific PCI device (default: all devices)
+    /// show only specific PCI or platform device (default: all devices)
     #[arg(short, long)]
     dev_slot: Option<String>,
 
@@ -52,15 +117,74 @@ pub struct Args {
     #[arg(short, long)]
     to_json: Option<String>,
 
+    /// save each device throttle reasons residency over the session to a CSV file on exit
+    #[arg(long)]
+    throttle_csv: Option<String>,
+
+    /// number of stats updates (at most the history length) over which DRM clients steadily growing memory is flagged as a possible leak
+    #[arg(long, default_value = "20")]
+    leak_window: usize,
+
+    /// per engine class weights to attribute GPU power to DRM clients, e.g. "compute=2,video=0.5" [default: 1.0 for all]
+    #[arg(long)]
+    power_weights: Option<String>,
+
+    /// alert rule on device or DRM client stats, can be repeated, e.g. "vram_used/vram_total > 0.9 for 30s", "throttle_reasons.thermal" or "client.engine.render > 80"
+    #[arg(long, action = ArgAction::Append)]
+    alert: Vec<String>,
+
+    /// command run (with sh -c) when an alert fires or clears, with QMASSA_ALERT* env vars set
+    #[arg(long)]
+    alert_cmd: Option<String>,
+
+    /// log alerts firing or clearing to syslog
+    #[arg(long, action = ArgAction::SetTrue)]
+    alert_syslog: bool,
+
+    /// pci.ids file for device names missing in udev hwdb [default: usual paths]
+    #[arg(long)]
+    pci_ids: Option<String>,
+
+    /// TOML config file with default settings [default: $XDG_CONFIG_HOME/qmassa/config.toml]
+    #[arg(short, long)]
+    config: Option<String>,
+
     /// file to log to when RUST_LOG is used [default: stderr (if not tty) or qmassa-<pid>.log]
     #[arg(short, long)]
     log_file: Option<String>,
+
+    #[command(subcommand)]
+    command: Option<Commands>,
+}
+
+// config file settings are only used for options not in the command-line
+fn apply_config(args: &mut Args, matches: &ArgMatches, cfg: &AppConfig)
+{
+    let from_cli = |id: &str| {
+        matches.value_source(id) == Some(ValueSource::CommandLine)
+    };
+
+    if let Some(ival) = cfg.ms_interval {
+        if !from_cli("ms_interval") {
+            args.ms_interval = ival;
+        }
+    }
+    if cfg.dev_slot.is_some() && !from_cli("dev_slot") {
+        args.dev_slot = cfg.dev_slot.clone();
+    }
+    if let Some(all) = cfg.all_clients {
+        if !from_cli("all_clients") {
+            args.all_clients = all;
+        }
+    }
 }
 
 fn main() -> Result<()>
 {
     // parse command-line args
-    let args = Args::parse();
+    let matches = Args::command().get_matches();
+    let mut args = Args::from_arg_matches(&matches)
+        .unwrap_or_else(|err| err.exit());
 
     // set up logging, if needed
     if env::var_os(env_logger::DEFAULT_FILTER_ENV).is_some() {
@@ -93,7 +217,12 @@ fn main() -> Result<()>
         }
     }
 
-    let base_pid: String;
+    // load config file, if any, for settings not in the command-line
+    let cfg = AppConfig::load(args.config.as_deref())
+        .context("Failed to load config file")?;
+    apply_config(&mut args, &matches, &cfg);
+
+    let mut base_pid: String;
     if args.pid.is_some() {
         base_pid = args.pid.clone().unwrap();
     } else {
@@ -105,20 +234,82 @@ fn main() -> Result<()>
     }
 
     // find all DRM subsystem devices
-    let mut qmds = DrmDevices::find_devices()
+    let mut qmds = DrmDevices::find_devices(args.pci_ids.as_deref())
         .context("Failed finding DRM devices")?;
     if qmds.is_empty() {
         bail!("No DRM devices found");
     }
+    // run command (if asked to) and only track its process tree
+    let mut child: Option<AppChild> = None;
+    let mut check: Option<AppCheck> = None;
+    let cmd = match &args.command {
+        Some(Commands::Run { cmd }) => Some(cmd),
+        Some(Commands::Check { limits, cmd }) => {
+            check = Some(AppCheck::from(limits)
+                .context("Failed to parse check limits")?);
+            Some(cmd)
+        },
+        None => None,
+    };
+    if let Some(cmd) = cmd {
+        let ch = process::Command::new(&cmd[0])
+            .args(&cmd[1..])
+            .spawn()
+            .with_context(|| format!("Failed to run command {:?}", cmd[0]))?;
+        // like time(1), let the command handle Ctrl-C and still report
+        unsafe { libc::signal(libc::SIGINT, libc::SIG_IGN); }
+
+        base_pid = ch.id().to_string();
+        args.pid = Some(base_pid.clone());
+        child = Some(AppChild::from(ch));
+    }
+
     // get DRM clients from pid process tree starting at base_pid
     qmds.set_clients_pid_tree(base_pid.as_str())
         .context("Failed to set DRM clients pid tree")?;
 
     // get app data from live system info
-    let appdata = AppData::from(qmds);
+    let mut appdata = AppData::from(qmds, cfg.history)
+        .context("Failed to set history length")?;
+    if let Some(pw) = &args.power_weights {
+        appdata.set_power_weights(pw)
+            .context("Failed to parse power weights")?;
+    }
+    appdata.set_leak_window(args.leak_window)
+        .context("Failed to set leak detection window")?;
+
+    // parse alert rules and how to notify them
+    let mut alerts = AppAlerts::new();
+    for rule in args.alert.iter() {
+        alerts.add_rule(rule)
+            .with_context(|| format!("Failed to parse alert rule {:?}", rule))?;
+    }
+    if let Some(cmd) = &args.alert_cmd {
+        alerts.set_hook(cmd);
+    }
+    alerts.set_syslog(args.alert_syslog);
 
-    // create tui app and run its mainloop
-    let mut app = App::from(appdata, args);
+    // create tui app and run its mainloop, or just wait for the command
+    let mut app = App::from(appdata, args, cfg)?;
+    app.set_alerts(alerts);
+    if let Some(ch) = child {
+        let mut code = app.run_command(ch)?;
+        if let Some(chk) = check {
+            let results = chk.results(app.summary());
+            let failed = results.iter().filter(|r| !r.passed).count();
+
+            eprintln!("\nqmassa: check {} ({} of {} limits violated)",
+                if failed > 0 { "FAILED" } else { "passed" },
+                failed, results.len());
+            for res in results.iter() {
+                eprint!("{}", res);
+            }
+            if failed > 0 && code == 0 {
+                code = CHECK_FAILED_EXIT_CODE;
+            }
+        }
+        process::exit(code);
+    }
     app.run()?;
 
     Ok(())
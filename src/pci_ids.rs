@@ -0,0 +1,153 @@
+use std::collections::HashMap;
+use std::path::Path;
+use std::fs;
+
+use anyhow::Result;
+use log::debug;
+
+
+// usual locations of pci.ids from hwdata/pciutils in Linux distributions
+const PCI_IDS_PATHS: &[&str] = &[
+    "/usr/share/hwdata/pci.ids",
+    "/usr/share/misc/pci.ids",
+    "/usr/share/pci.ids",
+    "/var/lib/pciutils/pci.ids",
+];
+
+#[derive(Debug)]
+struct PciIdsDevice
+{
+    name: String,
+    subsystems: HashMap<String, String>,    // "svid:sdid" => name
+}
+
+#[derive(Debug)]
+struct PciIdsVendor
+{
+    name: String,
+    devices: HashMap<String, PciIdsDevice>,
+}
+
+#[derive(Debug)]
+pub struct PciIds
+{
+    vendors: HashMap<String, PciIdsVendor>,
+}
+
+impl PciIds
+{
+    pub fn vendor_name(&self, vendor_id: &str) -> Option<&str>
+    {
+        let vdr = self.vendors.get(&vendor_id.to_lowercase())?;
+
+        Some(&vdr.name)
+    }
+
+    pub fn device_name(&self, vendor_id: &str, device_id: &str) -> Option<&str>
+    {
+        let vdr = self.vendors.get(&vendor_id.to_lowercase())?;
+        let dev = vdr.devices.get(&device_id.to_lowercase())?;
+
+        Some(&dev.name)
+    }
+
+    pub fn subsystem_name(&self, vendor_id: &str, device_id: &str,
+        subsys_vendor_id: &str, subsys_device_id: &str) -> Option<&str>
+    {
+        let vdr = self.vendors.get(&vendor_id.to_lowercase())?;
+        let dev = vdr.devices.get(&device_id.to_lowercase())?;
+        let sn = dev.subsystems.get(&format!("{}:{}",
+            subsys_vendor_id.to_lowercase(), subsys_device_id.to_lowercase()))?;
+
+        Some(sn)
+    }
+
+    // splits "<id>  <name>" lines, ids are always 4 hex digits
+    fn id_and_name(line: &str) -> Option<(String, String)>
+    {
+        let (id, name) = line.split_once(' ')?;
+
+        Some((id.to_lowercase(), name.trim().to_string()))
+    }
+
+    pub fn from_file(fpath: &Path) -> Result<PciIds>
+    {
+        let all_str = fs::read(fpath)?;
+        // some entries aren't valid UTF-8 in older files
+        let all_str = String::from_utf8_lossy(&all_str);
+
+        let mut vendors: HashMap<String, PciIdsVendor> = HashMap::new();
+        let mut cur_vdr: Option<String> = None;
+        let mut cur_dev: Option<String> = None;
+
+        for line in all_str.lines() {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            // device classes list comes after all vendors
+            if line.starts_with("C ") {
+                break;
+            }
+
+            if let Some(sline) = line.strip_prefix("\t\t") {
+                // subsystem: "\t\t<svid> <sdid>  <name>"
+                let ent = sline.split_once(' ').and_then(|(svid, rest)|
+                    PciIds::id_and_name(rest).map(|(sdid, name)|
+                        (format!("{}:{}", svid.to_lowercase(), sdid), name)));
+                if let (Some(vid), Some(did), Some((sid, name))) =
+                    (&cur_vdr, &cur_dev, ent) {
+                    let dev = vendors.get_mut(vid).unwrap()
+                        .devices.get_mut(did).unwrap();
+                    dev.subsystems.insert(sid, name);
+                }
+            } else if let Some(dline) = line.strip_prefix('\t') {
+                // device: "\t<did>  <name>"
+                if let (Some(vid), Some((did, name))) =
+                    (&cur_vdr, PciIds::id_and_name(dline)) {
+                    vendors.get_mut(vid).unwrap().devices.insert(did.clone(),
+                        PciIdsDevice {
+                            name,
+                            subsystems: HashMap::new(),
+                        });
+                    cur_dev = Some(did);
+                }
+            } else if let Some((vid, name)) = PciIds::id_and_name(line) {
+                // vendor: "<vid>  <name>"
+                vendors.insert(vid.clone(), PciIdsVendor {
+                    name,
+                    devices: HashMap::new(),
+                });
+                cur_vdr = Some(vid);
+                cur_dev = None;
+            }
+        }
+
+        Ok(PciIds {
+            vendors,
+        })
+    }
+
+    pub fn find(fpath: Option<&str>) -> Option<PciIds>
+    {
+        let paths: Vec<&str> = if let Some(fp) = fpath {
+            vec![fp]
+        } else {
+            PCI_IDS_PATHS.to_vec()
+        };
+
+        for fp in paths {
+            match PciIds::from_file(Path::new(fp)) {
+                Ok(pids) => {
+                    debug!("INF: using PCI IDs from {:?}", fp);
+                    return Some(pids);
+                },
+                Err(err) => {
+                    debug!("ERR: failed to read PCI IDs from {:?}: {:?}",
+                        fp, err);
+                }
+            }
+        }
+
+        None
+    }
+}
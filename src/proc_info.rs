@@ -148,8 +148,9 @@ impl ProcInfo
         for et in fddir.read_dir()? {
             let et = et?;
 
+            let mut mj: u32 = 0;
             let mut mn: u32 = 0;
-            let is_drm_fd = DrmFdinfo::is_drm_fd(&et.path(), &mut mn);
+            let is_drm_fd = DrmFdinfo::is_drm_fd(&et.path(), &mut mj, &mut mn);
             if let Err(err) = is_drm_fd {
                 debug!("ERR: failed to find fd {:?}: {:?}", et.path(), err);
                 continue;
@@ -160,7 +161,7 @@ impl ProcInfo
             }
 
             let fipath = fdinfodir.join(et.path().file_name().unwrap());
-            let finfo = DrmFdinfo::from(&fipath, mn);
+            let finfo = DrmFdinfo::from(&fipath, mj, mn);
             if let Err(err) = finfo {
                 debug!("ERR: failed to parse DRM fdinfo {:?}: {:?}", fipath, err);
                 continue;
@@ -168,7 +169,7 @@ impl ProcInfo
             let finfo = finfo.unwrap();
 
             if finfo.pci_dev.is_empty() {
-                debug!("INF: DRM fdinfo {:?} with no PCI dev, ignoring.",
+                debug!("INF: DRM fdinfo {:?} with no device, ignoring.",
                     fipath);
                 continue;
             }